@@ -0,0 +1,163 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+use miette::{IntoDiagnostic, Result};
+use tokio::fs;
+
+use crate::{digest::sha256_file, dirs::get_project_dirs};
+
+/// A content-addressed cache of downloaded release assets.
+///
+/// Assets are stored as `downloads/blobs/{sha256}`, with
+/// `downloads/assets/{asset_id}` recording which blob belongs to which
+/// GitHub release asset.
+pub struct DownloadCache {
+    dir: PathBuf,
+}
+
+/// A verified asset in the cache.
+pub struct CachedAsset {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+pub struct CacheUsage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl DownloadCache {
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            dir: get_project_dirs()?.cache_dir().join("downloads"),
+        })
+    }
+
+    fn blobs_dir(&self) -> PathBuf {
+        self.dir.join("blobs")
+    }
+
+    fn asset_path(&self, asset_id: u64) -> PathBuf {
+        self.dir.join("assets").join(asset_id.to_string())
+    }
+
+    /// The directory partially downloaded assets are kept in.
+    pub fn partial_dir(&self) -> PathBuf {
+        self.dir.join("partial")
+    }
+
+    /// Looks up the asset with the GitHub ID `asset_id`.
+    ///
+    /// The cached file is always checked against its digest (and against
+    /// `expected_sha256`, if given); entries that fail the check are evicted
+    /// and treated as misses.
+    pub async fn get(&self, asset_id: u64, expected_sha256: Option<&str>) -> Result<Option<CachedAsset>> {
+        let Ok(sha256) = fs::read_to_string(self.asset_path(asset_id)).await else {
+            return Ok(None);
+        };
+        let sha256 = sha256.trim().to_string();
+        if expected_sha256.is_some_and(|expected| expected != sha256) {
+            debug!("Cached asset {asset_id} has digest {sha256}, which isn't the expected one");
+            return Ok(None);
+        }
+
+        let path = self.blobs_dir().join(&sha256);
+        if !fs::try_exists(&path).await.into_diagnostic()? {
+            return Ok(None);
+        }
+        if sha256_file(&path).await? != sha256 {
+            warn!("Cached download {} is corrupted, removing it.", path.display());
+            fs::remove_file(&path).await.into_diagnostic()?;
+            return Ok(None);
+        }
+
+        Ok(Some(CachedAsset { path, sha256 }))
+    }
+
+    /// Adds the downloaded asset at `path`, whose SHA-256 digest is
+    /// `sha256`, to the cache. Only assets that passed every check should be
+    /// added.
+    pub async fn insert(&self, asset_id: u64, path: &Path, sha256: &str) -> Result<()> {
+        let blob_path = self.blobs_dir().join(sha256);
+        fs::create_dir_all(self.blobs_dir()).await.into_diagnostic()?;
+        if !fs::try_exists(&blob_path).await.into_diagnostic()? {
+            fs::copy(path, &blob_path).await.into_diagnostic()?;
+        }
+
+        let asset_path = self.asset_path(asset_id);
+        fs::create_dir_all(asset_path.parent().unwrap())
+            .await
+            .into_diagnostic()?;
+        fs::write(asset_path, sha256).await.into_diagnostic()?;
+        Ok(())
+    }
+
+    /// Adds up the size of everything in the cache.
+    pub async fn usage(&self) -> Result<CacheUsage> {
+        let mut usage = CacheUsage { entries: 0, bytes: 0 };
+        for (_, size) in self.blobs().await? {
+            usage.entries += 1;
+            usage.bytes += size;
+        }
+        for (_, size) in list_files(&self.partial_dir()).await? {
+            usage.bytes += size;
+        }
+        Ok(usage)
+    }
+
+    /// Removes every cached download whose digest isn't in `keep`, along with
+    /// any partial downloads, returning how much was freed.
+    pub async fn prune(&self, keep: &HashSet<String>) -> Result<CacheUsage> {
+        let mut freed = CacheUsage { entries: 0, bytes: 0 };
+        for (path, size) in self.blobs().await? {
+            let sha256 = path.file_name().unwrap().to_string_lossy().to_string();
+            if keep.contains(&sha256) {
+                continue;
+            }
+            fs::remove_file(&path).await.into_diagnostic()?;
+            freed.entries += 1;
+            freed.bytes += size;
+        }
+        for (path, size) in list_files(&self.partial_dir()).await? {
+            fs::remove_file(&path).await.into_diagnostic()?;
+            freed.bytes += size;
+        }
+
+        // Drop the asset entries that now point at nothing.
+        for (path, _) in list_files(&self.dir.join("assets")).await? {
+            let sha256 = fs::read_to_string(&path).await.into_diagnostic()?;
+            if !fs::try_exists(self.blobs_dir().join(sha256.trim()))
+                .await
+                .into_diagnostic()?
+            {
+                fs::remove_file(&path).await.into_diagnostic()?;
+            }
+        }
+
+        Ok(freed)
+    }
+
+    async fn blobs(&self) -> Result<Vec<(PathBuf, u64)>> {
+        list_files(&self.blobs_dir()).await
+    }
+}
+
+/// Lists the files directly inside `dir` along with their sizes.
+async fn list_files(dir: &Path) -> Result<Vec<(PathBuf, u64)>> {
+    if !fs::try_exists(dir).await.into_diagnostic()? {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    let mut entries = fs::read_dir(dir).await.into_diagnostic()?;
+    while let Some(entry) = entries.next_entry().await.into_diagnostic()? {
+        let metadata = entry.metadata().await.into_diagnostic()?;
+        if metadata.is_file() {
+            files.push((entry.path(), metadata.len()));
+        }
+    }
+    Ok(files)
+}
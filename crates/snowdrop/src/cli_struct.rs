@@ -14,18 +14,124 @@ pub struct Cli {
 pub enum Command {
     /// Install a package.
     Install {
-        /// The name of the package you want to install
-        package: String,
+        /// The name of the package you want to install, optionally followed
+        /// by `@` and a release tag or semver requirement (e.g.
+        /// `ripgrep@13.0.0` or `bat@^0.22`)
+        #[clap(required_unless_present = "locked")]
+        package: Option<String>,
+
+        /// The release tag or semver requirement to install, instead of the
+        /// latest release
+        #[clap(long)]
+        version: Option<String>,
+
+        /// Whether to consider prereleases when resolving a semver
+        /// requirement
+        #[clap(long)]
+        pre: bool,
+
+        /// Install exactly what the project's `snowdrop.lock` pins, for
+        /// every package in it unless a package is given
+        #[clap(long, conflicts_with_all = ["version", "pre"])]
+        locked: bool,
 
         /// Whether to run a "dry-run". If this flag is set, then no
         /// files will be written
         #[clap(long)]
         dry_run: bool,
+
+        /// Install without asking for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    /// Uninstall a package.
+    Uninstall {
+        /// The name of the package you want to uninstall
+        package: String,
+
+        /// Whether to run a "dry-run". If this flag is set, then the
+        /// files that would be removed are printed instead
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Upgrade installed packages to their latest releases.
+    Upgrade {
+        /// The names of the packages you want to upgrade
+        packages: Vec<String>,
+
+        /// Upgrade every installed package
+        #[clap(long, conflicts_with = "packages")]
+        all: bool,
+
+        /// Upgrade without asking for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    /// Pin the packages in the project's `snowdrop.toml` in `snowdrop.lock`.
+    Lock {
+        /// Re-resolve every package, instead of keeping the existing pins
+        #[clap(long)]
+        upgrade: bool,
+    },
+
+    /// Install, upgrade and downgrade packages to match the project's
+    /// `snowdrop.lock`.
+    Sync {
+        /// Also uninstall packages that aren't in `snowdrop.lock`
+        #[clap(long)]
+        prune: bool,
+
+        /// Whether to run a "dry-run". If this flag is set, then the
+        /// changes are printed but not made
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Sync without asking for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    /// Make another installed version of a package the active one.
+    Switch {
+        /// The name of the package
+        package: String,
+
+        /// The tag of the installed version to switch to
+        version: String,
+    },
+
+    /// Switch a package back to the version that was active before the
+    /// current one.
+    Rollback {
+        /// The name of the package
+        package: String,
+    },
+
+    /// Show installed packages that have newer releases available.
+    Outdated,
+
+    /// List installed packages.
+    List,
+
+    /// Manage the download cache.
+    Cache {
+        #[command(subcommand)]
+        command: cache::CacheCommand,
     },
 
     /// Set a GitHub PAT for authentication.
     Auth,
 
+    /// Show information about a package.
+    Info {
+        /// The name of the package, optionally qualified with an index (e.g.
+        /// `internal/deploy-cli`)
+        package: String,
+    },
+
     /// Search the index for packages.
     Search {
         /// The query you want to make.
@@ -45,8 +151,33 @@ impl Command {
     pub async fn execute(&self) -> Result<()> {
         debug!("Command invoked: {:#?}", self);
         match self {
-            Self::Install { dry_run, package } => install::Install::execute(package, dry_run).await,
+            Self::Install {
+                locked: true,
+                package,
+                dry_run,
+                yes,
+                ..
+            } => install::Install::execute_locked(package.as_deref(), dry_run, yes).await,
+            Self::Install {
+                dry_run,
+                package: Some(package),
+                version,
+                pre,
+                yes,
+                ..
+            } => install::Install::execute(package, version, pre, dry_run, yes).await,
+            Self::Install { package: None, .. } => unreachable!("clap requires a package unless --locked is passed"),
+            Self::Uninstall { dry_run, package } => uninstall::Uninstall::execute(package, dry_run).await,
+            Self::Upgrade { packages, all, yes } => upgrade::Upgrade::execute(packages, all, yes).await,
+            Self::Lock { upgrade } => lock::Lock::execute(upgrade).await,
+            Self::Sync { prune, dry_run, yes } => sync::Sync::execute(prune, dry_run, yes).await,
+            Self::Switch { package, version } => switch::Switch::execute(package, version).await,
+            Self::Rollback { package } => rollback::Rollback::execute(package).await,
+            Self::Outdated => outdated::Outdated::execute().await,
+            Self::List => list::List::execute().await,
+            Self::Cache { command } => cache::Cache::execute(command).await,
             Self::Auth => auth::Auth::execute().await,
+            Self::Info { package } => info::Info::execute(package).await,
             Self::Search {
                 query,
                 min_score: minimum_score,
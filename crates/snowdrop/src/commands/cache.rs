@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+
+use clap::Subcommand;
+use colored::Colorize;
+use miette::Result;
+
+use crate::{cache::DownloadCache, receipts::Receipts, units::format_size};
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Show how much space the download cache is using.
+    Info,
+
+    /// Remove cached downloads that no installed package came from.
+    Prune {
+        /// Remove every cached download, including those of installed
+        /// packages
+        #[clap(long)]
+        all: bool,
+    },
+}
+
+pub struct Cache;
+
+impl Cache {
+    pub async fn execute(command: &CacheCommand) -> Result<()> {
+        let cache = DownloadCache::open()?;
+
+        match command {
+            CacheCommand::Info => {
+                let usage = cache.usage().await?;
+                println!(
+                    "{}",
+                    format!("{} cached downloads using {}.", usage.entries, format_size(usage.bytes)).bold()
+                );
+            }
+            CacheCommand::Prune { all } => {
+                let keep = if *all {
+                    HashSet::new()
+                } else {
+                    Receipts::open()?
+                        .list()
+                        .await?
+                        .into_iter()
+                        .map(|receipt| receipt.asset_sha256)
+                        .collect()
+                };
+                let freed = cache.prune(&keep).await?;
+                println!(
+                    "{}",
+                    format!(
+                        "Removed {} cached downloads, freeing {}.",
+                        freed.entries,
+                        format_size(freed.bytes)
+                    )
+                    .green()
+                    .bold()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
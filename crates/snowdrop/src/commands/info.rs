@@ -0,0 +1,59 @@
+use colored::Colorize;
+use miette::Result;
+
+use crate::{config::get_config, receipts::Receipts};
+
+pub struct Info;
+
+impl Info {
+    /// Shows what the index knows about a package, along with its latest
+    /// release and the installed version, if any.
+    pub async fn execute(package: &str) -> Result<()> {
+        let config = get_config()?;
+        let indexes = config.indexes().await?;
+        let package_metadata = indexes.get_package(package).await?;
+        let latest = package_metadata.get_latest_release().await?;
+        let installed = Receipts::open()?.get(&package_metadata.name).await?;
+
+        println!(
+            "{} {}",
+            package_metadata.pretty_name.blue().bold(),
+            format!("({})", package_metadata.name).dimmed()
+        );
+        if let Some(description) = &package_metadata.description {
+            println!("{description}");
+        }
+        println!();
+
+        let [owner, repo] = &package_metadata.repo;
+        print_field("Repository", &format!("https://github.com/{owner}/{repo}"));
+        if let Some(homepage) = &package_metadata.homepage {
+            print_field("Homepage", homepage);
+        }
+        if let Some(license) = &package_metadata.license {
+            print_field("License", license);
+        }
+        if !package_metadata.tags.is_empty() {
+            print_field("Tags", &package_metadata.tags.join(", "));
+        }
+        let provided_bins = package_metadata.provided_bins();
+        if !provided_bins.is_empty() {
+            print_field("Binaries", &provided_bins.join(", "));
+        }
+        if indexes.names().count() > 1 {
+            print_field("Index", &package_metadata.index);
+        }
+        print_field("Latest", &latest.tag_name.green().to_string());
+        match installed {
+            Some(receipt) if receipt.tag == latest.tag_name => print_field("Installed", &receipt.tag),
+            Some(receipt) => print_field("Installed", &receipt.tag.red().to_string()),
+            None => print_field("Installed", &"no".dimmed().to_string()),
+        }
+
+        Ok(())
+    }
+}
+
+fn print_field(label: &str, value: &str) {
+    println!("{} {value}", format!("{label:>10}:").bold());
+}
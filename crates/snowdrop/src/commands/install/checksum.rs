@@ -0,0 +1,354 @@
+use std::path::Path;
+
+use globset::Glob;
+use log::{info, warn};
+use miette::{Diagnostic, IntoDiagnostic, Result};
+use octocrab::models::repos::Asset;
+use thiserror::Error;
+use tokio::fs;
+
+use super::scoring;
+use crate::digest::{sha256_file, sha512_file};
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum ChecksumError {
+    #[error("No release asset matches the checksum pattern `{0}`")]
+    #[diagnostic(help("The package says its releases publish checksums, but this one doesn't"))]
+    MissingChecksumAsset(String),
+
+    #[error("`{checksum_file}` doesn't list a checksum for `{asset}`")]
+    NotListed { asset: String, checksum_file: String },
+
+    #[error("Checksum mismatch for `{asset}`")]
+    #[diagnostic(help(
+        "Expected {algorithm} {expected}, but the download had {actual}. The download may be corrupted or have been \
+         tampered with, so it won't be installed"
+    ))]
+    Mismatch {
+        asset: String,
+        algorithm: Algorithm,
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    /// Guesses the algorithm from the length of a hex digest.
+    fn from_hex_len(len: usize) -> Option<Self> {
+        match len {
+            64 => Some(Self::Sha256),
+            128 => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sha256 => write!(f, "SHA-256"),
+            Self::Sha512 => write!(f, "SHA-512"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedDigest {
+    pub algorithm: Algorithm,
+    pub hex: String,
+}
+
+/// The extensions of checksum files that only hold the checksum of the asset
+/// they're named after, like `{asset}.sha256`.
+const PER_ASSET_EXTENSIONS: [&str; 4] = ["sha256", "sha512", "sha256sum", "sha512sum"];
+
+/// Finds the release asset holding the checksum of `asset_name`.
+///
+/// If the package names a checksum asset pattern it must match something;
+/// otherwise per-asset files like `{asset}.sha256` are preferred over
+/// combined files like `SHA256SUMS` or `checksums.txt`.
+pub fn find_checksum_asset<'a>(
+    assets: &'a [Asset],
+    asset_name: &str,
+    pattern: Option<&str>,
+) -> Result<Option<&'a Asset>> {
+    if let Some(pattern) = pattern {
+        let glob = Glob::new(pattern).into_diagnostic()?.compile_matcher();
+        return match assets.iter().find(|asset| glob.is_match(&asset.name)) {
+            Some(asset) => Ok(Some(asset)),
+            None => Err(ChecksumError::MissingChecksumAsset(pattern.to_string()).into()),
+        };
+    }
+
+    for extension in PER_ASSET_EXTENSIONS {
+        let name = format!("{asset_name}.{extension}");
+        if let Some(asset) = assets.iter().find(|asset| asset.name == name) {
+            return Ok(Some(asset));
+        }
+    }
+    Ok(assets.iter().find(|asset| is_combined_checksum_file(&asset.name)))
+}
+
+/// Whether `name` looks like a checksum file covering several assets, as
+/// opposed to a signature or certificate for one.
+fn is_combined_checksum_file(name: &str) -> bool {
+    if scoring::tokenize(name).unusable == Some("signature") {
+        return false;
+    }
+    let name = name.to_lowercase();
+    name.starts_with("sha256sums") || name.starts_with("sha512sums") || name.contains("checksums")
+}
+
+/// Whether the checksum file named `checksum_name` only holds the checksum
+/// of `asset_name`.
+fn is_per_asset_file(checksum_name: &str, asset_name: &str) -> bool {
+    checksum_name
+        .strip_prefix(asset_name)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .is_some_and(|extension| PER_ASSET_EXTENSIONS.contains(&extension))
+}
+
+/// Finds the digest of `asset_name` in the contents of a checksum file.
+///
+/// GNU coreutils (`{hex}  {name}` or `{hex} *{name}`), BSD
+/// (`SHA256 ({name}) = {hex}`) and bare hex files are understood. A
+/// `per_asset` file holds nothing but the asset's digest, so its single line
+/// is used whatever name it lists, as that's often a build path or an
+/// unversioned name.
+pub fn parse(contents: &str, asset_name: &str, per_asset: bool) -> Option<ExpectedDigest> {
+    let lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>();
+
+    for line in &lines {
+        if let (Some(name), hex) = split_line(line) {
+            if file_name_matches(name, asset_name) {
+                return digest_from_hex(hex);
+            }
+        }
+    }
+
+    match lines.as_slice() {
+        [line] => match split_line(line) {
+            (None, hex) => digest_from_hex(hex),
+            (Some(_), hex) if per_asset => digest_from_hex(hex),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Splits a line of a checksum file into the file name it lists, if any,
+/// and the digest.
+fn split_line(line: &str) -> (Option<&str>, &str) {
+    // BSD style.
+    if let Some((name, hex)) = line.split_once(" (").and_then(|(_, rest)| rest.rsplit_once(") = ")) {
+        return (Some(name), hex);
+    }
+
+    // GNU style, or a lone digest.
+    match line.split_once(char::is_whitespace) {
+        Some((hex, name)) => (Some(name.trim_start().trim_start_matches('*')), hex),
+        None => (None, line),
+    }
+}
+
+fn file_name_matches(name: &str, asset_name: &str) -> bool {
+    let name = name.trim();
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    name == asset_name
+}
+
+fn digest_from_hex(hex: &str) -> Option<ExpectedDigest> {
+    let hex = hex.trim().to_lowercase();
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(ExpectedDigest {
+        algorithm: Algorithm::from_hex_len(hex.len())?,
+        hex,
+    })
+}
+
+/// Checks the downloaded asset at `path` against the downloaded checksum
+/// file at `checksum_path`.
+///
+/// If the checksum file doesn't mention the asset, this only fails when the
+/// checksum file was explicitly named by the package, or is what the
+/// release's signature covers (`required`).
+pub async fn verify(checksum_path: &Path, asset_name: &str, path: &Path, required: bool) -> Result<()> {
+    let contents = fs::read_to_string(checksum_path).await.into_diagnostic()?;
+    let checksum_name = checksum_path.file_name().unwrap().to_string_lossy().to_string();
+
+    let per_asset = is_per_asset_file(&checksum_name, asset_name);
+    let Some(expected) = parse(&contents, asset_name, per_asset) else {
+        let error = ChecksumError::NotListed {
+            asset: asset_name.to_string(),
+            checksum_file: checksum_name,
+        };
+        if required {
+            return Err(error.into());
+        }
+        warn!("{error}, skipping checksum verification.");
+        return Ok(());
+    };
+
+    let actual = match expected.algorithm {
+        Algorithm::Sha256 => sha256_file(path).await?,
+        Algorithm::Sha512 => sha512_file(path).await?,
+    };
+    if actual != expected.hex {
+        return Err(ChecksumError::Mismatch {
+            asset: asset_name.to_string(),
+            algorithm: expected.algorithm,
+            expected: expected.hex,
+            actual,
+        }
+        .into());
+    }
+
+    info!("Verified the {} checksum of {asset_name}.", expected.algorithm);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn sha256(hex: &str) -> Option<ExpectedDigest> {
+        Some(ExpectedDigest {
+            algorithm: Algorithm::Sha256,
+            hex: hex.to_string(),
+        })
+    }
+
+    #[test]
+    fn gnu_lines_are_parsed() {
+        let contents = format!(
+            "# comment\n{other}  tool-linux-arm64.tar.gz\n{SHA256}  tool-linux-amd64.tar.gz\n",
+            other = "0".repeat(64)
+        );
+        assert_eq!(parse(&contents, "tool-linux-amd64.tar.gz", false), sha256(SHA256));
+        assert_eq!(
+            parse(&contents, "tool-linux-arm64.tar.gz", false),
+            sha256(&"0".repeat(64))
+        );
+        assert_eq!(parse(&contents, "tool-darwin-amd64.tar.gz", false), None);
+
+        // Binary mode markers, leading directories and uppercase digests.
+        let contents = format!("{}  *dist/tool.zip\n", SHA256.to_uppercase());
+        assert_eq!(parse(&contents, "tool.zip", false), sha256(SHA256));
+    }
+
+    #[test]
+    fn bsd_lines_are_parsed() {
+        let contents = format!(
+            "SHA256 (tool.zip) = {SHA256}\nSHA256 (tool.tar.gz) = {}\n",
+            "1".repeat(64)
+        );
+        assert_eq!(parse(&contents, "tool.zip", false), sha256(SHA256));
+        assert_eq!(parse(&contents, "tool.tar.gz", false), sha256(&"1".repeat(64)));
+        assert_eq!(parse(&contents, "tool", false), None);
+    }
+
+    #[test]
+    fn lone_digests_are_parsed() {
+        assert_eq!(parse(&format!("{SHA256}\n"), "tool.zip", false), sha256(SHA256));
+        let sha512 = "a".repeat(128);
+        assert_eq!(
+            parse(&sha512, "tool.zip", false),
+            Some(ExpectedDigest {
+                algorithm: Algorithm::Sha512,
+                hex: sha512.clone(),
+            })
+        );
+        assert_eq!(parse("not-a-digest", "tool.zip", false), None);
+        assert_eq!(parse(&"a".repeat(40), "tool.zip", false), None);
+    }
+
+    #[test]
+    fn per_asset_files_are_used_whatever_name_they_list() {
+        let contents = format!("{SHA256}  target/release/tool\n");
+        assert_eq!(parse(&contents, "tool-1.0.0-linux.tar.gz", true), sha256(SHA256));
+        assert_eq!(parse(&contents, "tool-1.0.0-linux.tar.gz", false), None);
+
+        let contents = format!("SHA256 (tool.tar.gz) = {SHA256}\n");
+        assert_eq!(parse(&contents, "tool-1.0.0.tar.gz", true), sha256(SHA256));
+
+        // Only a single line is taken on trust.
+        let contents = format!("{SHA256}  tool-linux\n{SHA256}  tool-darwin\n");
+        assert_eq!(parse(&contents, "tool.tar.gz", true), None);
+
+        assert!(is_per_asset_file("tool.tar.gz.sha256", "tool.tar.gz"));
+        assert!(is_per_asset_file("tool.tar.gz.sha512sum", "tool.tar.gz"));
+        assert!(!is_per_asset_file("checksums.txt", "tool.tar.gz"));
+        assert!(!is_per_asset_file("tool.tar.gz.sig", "tool.tar.gz"));
+        assert!(!is_per_asset_file("other.tar.gz.sha256", "tool.tar.gz"));
+    }
+
+    /// Writes an empty asset and a checksum file for it into a temporary
+    /// directory, returning the directory and the paths of both.
+    async fn write_files(
+        asset_name: &str,
+        checksum_name: &str,
+        contents: &str,
+    ) -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let (asset_path, checksum_path) = (dir.path().join(asset_name), dir.path().join(checksum_name));
+        fs::write(&asset_path, "").await.unwrap();
+        fs::write(&checksum_path, contents).await.unwrap();
+        (dir, asset_path, checksum_path)
+    }
+
+    #[tokio::test]
+    async fn per_asset_files_are_verified() {
+        let contents = format!("{SHA256}  dist/tool\n");
+        let (_dir, asset, checksum) = write_files("tool-1.0.tar.gz", "tool-1.0.tar.gz.sha256", &contents).await;
+        verify(&checksum, "tool-1.0.tar.gz", &asset, false).await.unwrap();
+
+        let contents = format!("{}  dist/tool\n", "0".repeat(64));
+        let (_dir, asset, checksum) = write_files("tool-1.0.tar.gz", "tool-1.0.tar.gz.sha256", &contents).await;
+        let err = verify(&checksum, "tool-1.0.tar.gz", &asset, false).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ChecksumError>(),
+            Some(ChecksumError::Mismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn unlisted_assets_only_fail_when_required() {
+        let contents = format!("{SHA256}  tool-darwin.tar.gz\n");
+        let (_dir, asset, checksum) = write_files("tool-linux.tar.gz", "checksums.txt", &contents).await;
+        verify(&checksum, "tool-linux.tar.gz", &asset, false).await.unwrap();
+
+        let err = verify(&checksum, "tool-linux.tar.gz", &asset, true).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ChecksumError>(),
+            Some(ChecksumError::NotListed { .. })
+        ));
+    }
+
+    #[test]
+    fn signatures_are_not_checksum_files() {
+        assert!(is_combined_checksum_file("checksums.txt"));
+        assert!(is_combined_checksum_file("tool_1.0.0_checksums.txt"));
+        assert!(is_combined_checksum_file("SHA256SUMS"));
+        assert!(is_combined_checksum_file("sha512sums.txt"));
+        assert!(!is_combined_checksum_file("checksums.txt.sig"));
+        assert!(!is_combined_checksum_file("checksums.txt.pem"));
+        assert!(!is_combined_checksum_file("SHA256SUMS.asc"));
+        assert!(!is_combined_checksum_file("tool-linux-amd64.tar.gz"));
+    }
+}
@@ -0,0 +1,62 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use miette::{IntoDiagnostic, Result, WrapErr};
+
+use crate::dirs::get_project_dirs;
+
+/// The placeholder that stands in for the release version in a remembered
+/// asset name.
+const VERSION_PLACEHOLDER: &str = "{version}";
+
+/// The assets users picked by hand for packages whose naming scheme didn't
+/// match anything, so they aren't asked again on every upgrade.
+///
+/// Choices are stored as asset names with the release version replaced by
+/// a placeholder, in `asset_choices.toml` in the project data directory.
+pub struct AssetChoices {
+    path: PathBuf,
+    choices: BTreeMap<String, String>,
+}
+
+impl AssetChoices {
+    pub fn open() -> Result<Self> {
+        let path = get_project_dirs()?.data_dir().join("asset_choices.toml");
+        let choices = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to parse {}", path.display()))?,
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(Self { path, choices })
+    }
+
+    /// Gets the asset name that was previously chosen for `package`, adapted
+    /// to the release tagged `tag`.
+    pub fn get(&self, package: &str, tag: &str) -> Option<String> {
+        let template = self.choices.get(package)?;
+        Some(template.replace(VERSION_PLACEHOLDER, version_of(tag)))
+    }
+
+    /// Remembers that `asset_name` was chosen for `package` in the release
+    /// tagged `tag`.
+    pub fn remember(&mut self, package: &str, tag: &str, asset_name: &str) -> Result<()> {
+        let version = version_of(tag);
+        // Short versions like `1` would also match unrelated parts of the
+        // name, such as the `1` in `x86_64-v1`, so those are stored as is.
+        let template = match version.contains('.') {
+            true => asset_name.replace(version, VERSION_PLACEHOLDER),
+            false => asset_name.to_string(),
+        };
+        self.choices.insert(package.to_string(), template);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).into_diagnostic()?;
+        }
+        fs::write(&self.path, toml::to_string(&self.choices).into_diagnostic()?).into_diagnostic()
+    }
+}
+
+/// The version part of a tag, which is what usually appears in asset names.
+fn version_of(tag: &str) -> &str {
+    tag.strip_prefix(['v', 'V']).unwrap_or(tag)
+}
@@ -0,0 +1,141 @@
+use std::{io, path::Path};
+
+use colored::Colorize;
+use log::{debug, info, warn};
+use miette::{Diagnostic, IntoDiagnostic, Report, Result, WrapErr};
+use octocrab::models::repos::Asset;
+use reqwest::{header::RANGE, StatusCode};
+use thiserror::Error;
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{cache::DownloadCache, progress::download_bar};
+
+/// How many times an interrupted download is resumed before giving up.
+const MAX_ATTEMPTS: usize = 3;
+
+/// Streams `asset` to `destination`, showing a progress bar.
+///
+/// The asset is first written to `{asset_id}.part` in the cache's partial
+/// download directory, which is only moved into place once the download has
+/// completed. If a partial file is already there (because an earlier
+/// download was interrupted), the download is resumed from where it left off
+/// using an HTTP range request.
+pub async fn download_asset(asset: &Asset, destination: &Path) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .user_agent(format!("SnowdropCLI/{}", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into_diagnostic()?;
+
+    let partial_dir = DownloadCache::open()?.partial_dir();
+    fs::create_dir_all(&partial_dir).await.into_diagnostic()?;
+    let part_path = partial_dir.join(format!("{}.part", asset.id.0));
+
+    info!("Downloading {}.", asset.name.bold());
+    debug!("Downloading asset from `{}`", asset.browser_download_url);
+    let mut attempt = 1;
+    loop {
+        match download_to_part(&client, asset, &part_path).await {
+            Ok(()) => break,
+            Err(err @ (DownloadError::Interrupted(_) | DownloadError::InvalidPartial)) if attempt < MAX_ATTEMPTS => {
+                warn!("Download of {} failed ({err}), resuming...", asset.name.bold());
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(Report::new(err)).wrap_err_with(|| format!("failed to download asset `{}`", asset.name))
+            }
+        }
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).await.into_diagnostic()?;
+    }
+    // The partial directory and the destination are usually on the same
+    // filesystem, but fall back to copying if they aren't.
+    if fs::rename(&part_path, destination).await.is_err() {
+        fs::copy(&part_path, destination).await.into_diagnostic()?;
+        fs::remove_file(&part_path).await.into_diagnostic()?;
+    }
+    Ok(())
+}
+
+#[derive(Error, Diagnostic, Debug)]
+enum DownloadError {
+    /// The download stopped part of the way through, and can be resumed.
+    #[error("the download was interrupted")]
+    Interrupted(#[source] reqwest::Error),
+
+    #[error("the partial download was invalid")]
+    InvalidPartial,
+
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Downloads `asset` into `part_path`, resuming from whatever is already in
+/// it.
+async fn download_to_part(client: &reqwest::Client, asset: &Asset, part_path: &Path) -> Result<(), DownloadError> {
+    let existing = match fs::metadata(part_path).await {
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    };
+    let mut request = client.get(asset.browser_download_url.clone());
+    if existing > 0 {
+        debug!("Resuming download from byte {existing}");
+        request = request.header(RANGE, format!("bytes={existing}-"));
+    }
+    let mut response = request.send().await?;
+
+    let (mut file, start) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => (OpenOptions::new().append(true).open(part_path).await?, existing),
+        // The partial file already holds the whole asset.
+        StatusCode::RANGE_NOT_SATISFIABLE if existing == asset.size.max(0) as u64 => return Ok(()),
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            // The partial file is bigger than the asset, so it must be stale.
+            fs::remove_file(part_path).await?;
+            return Err(DownloadError::InvalidPartial);
+        }
+        _ => {
+            response = response.error_for_status()?;
+            (File::create(part_path).await?, 0)
+        }
+    };
+
+    let len = response
+        .content_length()
+        .map_or(asset.size.max(0) as u64, |len| start + len);
+    let bar = download_bar(&asset.name, len);
+    bar.set_position(start);
+
+    let result = async {
+        while let Some(chunk) = response.chunk().await.map_err(DownloadError::Interrupted)? {
+            file.write_all(&chunk).await?;
+            bar.inc(chunk.len() as u64);
+        }
+        file.flush().await?;
+        Ok(())
+    }
+    .await;
+    bar.finish_and_clear();
+    result
+}
+
+/// Marks the file at `path` as executable by everyone who can read it.
+#[cfg(unix)]
+pub async fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path).await.into_diagnostic()?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    fs::set_permissions(path, permissions).await.into_diagnostic()
+}
+
+#[cfg(not(unix))]
+pub async fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
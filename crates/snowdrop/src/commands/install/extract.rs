@@ -0,0 +1,279 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use log::debug;
+use miette::{IntoDiagnostic, Result, WrapErr};
+use xz2::read::XzDecoder;
+
+/// The archive formats that release assets can be unpacked from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarXz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detects the archive format of the file at `path`, first by its
+    /// extension and then by its magic bytes.
+    ///
+    /// Returns `None` if the file doesn't look like an archive, in which case
+    /// it should be treated as a bare executable.
+    pub fn detect(path: &Path) -> Result<Option<Self>> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if let Some(format) = Self::from_file_name(&file_name) {
+            return Ok(Some(format));
+        }
+
+        let magic = read_magic(path)?;
+        if magic.starts_with(b"PK\x03\x04") {
+            return Ok(Some(Self::Zip));
+        }
+
+        // A compressed file is only a tarball if it decompresses to a tar
+        // archive, whose header has `ustar` at offset 257.
+        let Some(compression) = Compression::from_magic(&magic) else {
+            return Ok(None);
+        };
+        let mut header = Vec::new();
+        // A file that can't be decompressed isn't a tarball either way, and
+        // decompressing it later will report why.
+        let _ = compression
+            .decoder(File::open(path).into_diagnostic()?)?
+            .take(262)
+            .read_to_end(&mut header);
+        Ok(header
+            .get(257..262)
+            .is_some_and(|magic| magic == b"ustar")
+            .then_some(compression.tarball()))
+    }
+
+    /// Detects the archive format from a file name alone.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let file_name = file_name.to_lowercase();
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if file_name.ends_with(".tar.xz") || file_name.ends_with(".txz") {
+            Some(Self::TarXz)
+        } else if file_name.ends_with(".tar.zst") || file_name.ends_with(".tzst") {
+            Some(Self::TarZst)
+        } else if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// Unpacks the archive at `archive` into the `destination` directory.
+    pub fn extract(self, archive: &Path, destination: &Path) -> Result<()> {
+        debug!(
+            "Extracting {} ({self:?}) into {}",
+            archive.display(),
+            destination.display()
+        );
+        fs::create_dir_all(destination).into_diagnostic()?;
+        let file = File::open(archive).into_diagnostic()?;
+
+        match self {
+            Self::TarGz => unpack_tar(Compression::Gz.decoder(file)?, destination),
+            Self::TarXz => unpack_tar(Compression::Xz.decoder(file)?, destination),
+            Self::TarZst => unpack_tar(Compression::Zst.decoder(file)?, destination),
+            Self::Zip => unpack_zip(file, destination),
+        }
+        .wrap_err_with(|| format!("failed to extract {}", archive.display()))
+    }
+}
+
+/// The formats a release asset that's a single compressed file, rather than
+/// an archive, can be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gz,
+    Xz,
+    Zst,
+}
+
+impl Compression {
+    /// Detects whether the file at `path` is compressed, first by its
+    /// extension and then by its magic bytes. Archives should be ruled out
+    /// with [`ArchiveFormat::detect`] first.
+    pub fn detect(path: &Path) -> Result<Option<Self>> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if file_name.ends_with(".gz") {
+            return Ok(Some(Self::Gz));
+        } else if file_name.ends_with(".xz") {
+            return Ok(Some(Self::Xz));
+        } else if file_name.ends_with(".zst") {
+            return Ok(Some(Self::Zst));
+        }
+        Ok(Self::from_magic(&read_magic(path)?))
+    }
+
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gz)
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Self::Xz)
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zst)
+        } else {
+            None
+        }
+    }
+
+    /// The tarball format compressed with this.
+    const fn tarball(self) -> ArchiveFormat {
+        match self {
+            Self::Gz => ArchiveFormat::TarGz,
+            Self::Xz => ArchiveFormat::TarXz,
+            Self::Zst => ArchiveFormat::TarZst,
+        }
+    }
+
+    fn decoder(self, file: File) -> Result<Box<dyn Read>> {
+        Ok(match self {
+            Self::Gz => Box::new(GzDecoder::new(file)),
+            Self::Xz => Box::new(XzDecoder::new(file)),
+            Self::Zst => Box::new(zstd::stream::read::Decoder::new(file).into_diagnostic()?),
+        })
+    }
+
+    /// Decompresses the file at `compressed` into the file at `output`.
+    pub fn decompress(self, compressed: &Path, output: &Path) -> Result<()> {
+        debug!(
+            "Decompressing {} ({self:?}) into {}",
+            compressed.display(),
+            output.display()
+        );
+        let mut decoder = self.decoder(File::open(compressed).into_diagnostic()?)?;
+        let mut output = File::create(output).into_diagnostic()?;
+        io::copy(&mut decoder, &mut output)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to decompress {}", compressed.display()))?;
+        Ok(())
+    }
+}
+
+/// Reads the first few bytes of the file at `path`, which identify its
+/// format.
+fn read_magic(path: &Path) -> Result<Vec<u8>> {
+    let mut magic = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(6).read_to_end(&mut magic))
+        .into_diagnostic()?;
+    Ok(magic)
+}
+
+fn unpack_tar(reader: impl Read, destination: &Path) -> Result<()> {
+    tar::Archive::new(reader).unpack(destination).into_diagnostic()
+}
+
+fn unpack_zip(file: File, destination: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(file).into_diagnostic()?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).into_diagnostic()?;
+        // `enclosed_name` rejects entries that would escape the destination.
+        let Some(relative_path) = entry.enclosed_name().map(Path::to_path_buf) else {
+            debug!("Skipping zip entry with unsafe path `{}`", entry.name());
+            continue;
+        };
+        let path = destination.join(relative_path);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path).into_diagnostic()?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).into_diagnostic()?;
+        }
+        let mut output = File::create(&path).into_diagnostic()?;
+        io::copy(&mut entry, &mut output).into_diagnostic()?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).into_diagnostic()?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the directory that an archive's contents actually live in.
+///
+/// Many archives wrap everything in a single top-level directory (such as
+/// `ripgrep-13.0.0-x86_64-unknown-linux-musl/`), which is skipped over here.
+pub fn content_root(staging: &Path) -> Result<PathBuf> {
+    let mut entries = fs::read_dir(staging)
+        .into_diagnostic()?
+        .collect::<Result<Vec<_>, _>>()
+        .into_diagnostic()?;
+
+    if entries.len() == 1 && entries[0].file_type().into_diagnostic()?.is_dir() {
+        return Ok(entries.remove(0).path());
+    }
+    Ok(staging.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression as Level};
+
+    use super::*;
+
+    fn gzip(path: &Path, contents: &[u8]) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Level::default());
+        encoder.write_all(contents).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn bare_compressed_executable_is_not_a_tarball() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tool-linux-amd64.gz");
+        gzip(&path, b"\x7fELF not really an executable");
+
+        assert_eq!(ArchiveFormat::detect(&path).unwrap(), None);
+        assert_eq!(Compression::detect(&path).unwrap(), Some(Compression::Gz));
+
+        let output = dir.path().join("tool");
+        Compression::Gz.decompress(&path, &output).unwrap();
+        assert_eq!(fs::read(output).unwrap(), b"\x7fELF not really an executable");
+    }
+
+    #[test]
+    fn tarball_is_detected_by_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tarball = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_cksum();
+        tarball.append_data(&mut header, "tool", &b"tool"[..]).unwrap();
+
+        // No extension, so only the contents can give it away.
+        let path = dir.path().join("tool-linux-amd64");
+        gzip(&path, &tarball.into_inner().unwrap());
+        assert_eq!(ArchiveFormat::detect(&path).unwrap(), Some(ArchiveFormat::TarGz));
+    }
+
+    #[test]
+    fn archives_are_detected_by_name() {
+        assert_eq!(ArchiveFormat::from_file_name("rg.tar.gz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_file_name("rg.TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_file_name("rg.tar.zst"), Some(ArchiveFormat::TarZst));
+        assert_eq!(ArchiveFormat::from_file_name("rg.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::from_file_name("rg.gz"), None);
+    }
+}
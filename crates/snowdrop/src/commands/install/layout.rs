@@ -0,0 +1,609 @@
+use std::{
+    env, fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use index_client::metadata::{Bin, Layout, PackageMetadata};
+use log::debug;
+use miette::{miette, IntoDiagnostic, Result};
+use walkdir::WalkDir;
+
+use super::extract;
+use crate::dirs::{get_bin_dir, get_share_dir};
+
+/// What a file from a release asset is installed as, which decides where it
+/// ends up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Bin,
+    ManPage { section: char },
+    Completion(Shell),
+    License,
+    Data,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A file to install from an extracted release asset.
+pub struct LayoutFile {
+    pub source: PathBuf,
+    pub kind: FileKind,
+
+    /// The path to install the file at, relative to the directory for its
+    /// kind.
+    pub name: String,
+}
+
+impl FileKind {
+    /// The directory files of this kind are installed into.
+    pub fn dir(self, package: &str) -> Result<PathBuf> {
+        let share_dir = get_share_dir()?;
+        Ok(match self {
+            Self::Bin => get_bin_dir()?,
+            Self::ManPage { section } => share_dir.join("man").join(format!("man{section}")),
+            Self::Completion(Shell::Bash) => share_dir.join("bash-completion").join("completions"),
+            Self::Completion(Shell::Zsh) => share_dir.join("zsh").join("site-functions"),
+            Self::Completion(Shell::Fish) => share_dir.join("fish").join("vendor_completions.d"),
+            Self::License => share_dir.join("licenses").join(package),
+            Self::Data => share_dir.join(package),
+        })
+    }
+}
+
+impl LayoutFile {
+    pub fn destination(&self, package: &str) -> Result<PathBuf> {
+        Ok(self.kind.dir(package)?.join(&self.name))
+    }
+}
+
+/// A file in an extracted archive.
+struct ArchiveFile {
+    source: PathBuf,
+
+    /// The file's path inside the archive, with leading directories stripped
+    /// and `/` as the separator.
+    relative: String,
+}
+
+impl ArchiveFile {
+    fn file_name(&self) -> &str {
+        self.relative.rsplit('/').next().unwrap_or(&self.relative)
+    }
+}
+
+/// Works out which files in an extracted archive should be installed, and
+/// as what.
+///
+/// The package's layout is followed where it has one. Executables that
+/// aren't covered by it are taken from the package's `bins`, or found
+/// automatically.
+pub fn collect(extracted: &Path, package_metadata: &PackageMetadata) -> Result<Vec<LayoutFile>> {
+    let default_layout = Layout::default();
+    let layout = package_metadata.layout.as_ref().unwrap_or(&default_layout);
+    let files = list_files(extracted, layout.strip_components)?;
+    let renames = Renames::new(layout)?;
+
+    let mut collected = if !layout.bins.is_empty() {
+        let globs = glob_set(&layout.bins)?;
+        let bins = files
+            .iter()
+            .filter(|file| globs.is_match(&file.relative))
+            .map(|file| {
+                Ok(LayoutFile {
+                    source: file.source.clone(),
+                    kind: FileKind::Bin,
+                    name: executable_name(&renames.name(file, file.file_name())?),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if bins.is_empty() {
+            return Err(miette!(
+                "None of the package's binary globs matched a file in the archive"
+            ));
+        }
+        bins
+    } else if !package_metadata.bins.is_empty() {
+        find_bins(&files, &package_metadata.bins)?
+    } else {
+        find_executables(&files, &package_metadata.name, layout)?
+    };
+
+    let man_pages = glob_set(&layout.man_pages)?;
+    let completions = [
+        (Shell::Bash, glob_set(&layout.completions.bash)?),
+        (Shell::Zsh, glob_set(&layout.completions.zsh)?),
+        (Shell::Fish, glob_set(&layout.completions.fish)?),
+    ];
+    let licenses = glob_set(&layout.licenses)?;
+    let data = glob_set(&layout.data)?;
+    for file in &files {
+        if collected.iter().any(|collected| collected.source == file.source) {
+            continue;
+        }
+
+        let kind = if man_pages.is_match(&file.relative) {
+            Some(FileKind::ManPage {
+                section: man_section(file.file_name()),
+            })
+        } else if let Some((shell, _)) = completions.iter().find(|(_, globs)| globs.is_match(&file.relative)) {
+            Some(FileKind::Completion(*shell))
+        } else if licenses.is_match(&file.relative) {
+            Some(FileKind::License)
+        } else if data.is_match(&file.relative) {
+            Some(FileKind::Data)
+        } else {
+            None
+        };
+        let (kind, name) = match kind {
+            // Data files keep their place in the archive's directory tree.
+            Some(FileKind::Data) => (FileKind::Data, renames.name(file, &file.relative)?),
+            Some(kind) => (kind, renames.name(file, file.file_name())?),
+            None => match by_convention(file, layout) {
+                Some((kind, name)) => (kind, renames.name(file, &name)?),
+                None => continue,
+            },
+        };
+        debug!("Laying out {} as {kind:?}", file.relative);
+        collected.push(LayoutFile {
+            source: file.source.clone(),
+            kind,
+            name,
+        });
+    }
+
+    Ok(collected)
+}
+
+/// Lists the files in an extracted archive, stripping `strip_components`
+/// leading directories from their paths, or a single wrapping directory if
+/// it isn't set.
+fn list_files(extracted: &Path, strip_components: Option<usize>) -> Result<Vec<ArchiveFile>> {
+    let (root, strip) = match strip_components {
+        Some(strip) => (extracted.to_path_buf(), strip),
+        None => (extract::content_root(extracted)?, 0),
+    };
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&root).follow_links(false).sort_by_file_name() {
+        let entry = entry.into_diagnostic()?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let components = entry
+            .path()
+            .strip_prefix(&root)
+            .into_diagnostic()?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        if components.len() <= strip {
+            debug!("Skipping {}, which is stripped away entirely", entry.path().display());
+            continue;
+        }
+        files.push(ArchiveFile {
+            relative: components[strip..].join("/"),
+            source: entry.into_path(),
+        });
+    }
+    Ok(files)
+}
+
+/// Locates the executables in an archive automatically.
+///
+/// Files marked as executable (or `.exe` files on Windows) are preferred; if
+/// there are none, a file named after the package is used instead, as zip
+/// archives frequently lose their permission bits. Completion scripts are
+/// sometimes marked as executable too, so they're never picked.
+fn find_executables(files: &[ArchiveFile], package_name: &str, layout: &Layout) -> Result<Vec<LayoutFile>> {
+    let bin = |file: &ArchiveFile| LayoutFile {
+        source: file.source.clone(),
+        kind: FileKind::Bin,
+        name: file.file_name().to_string(),
+    };
+
+    let executables = files
+        .iter()
+        .filter(|file| is_executable(&file.source) && by_convention(file, layout).is_none())
+        .map(bin)
+        .collect::<Vec<_>>();
+    if !executables.is_empty() {
+        return Ok(executables);
+    }
+
+    let expected_name = executable_name(package_name);
+    files
+        .iter()
+        .find(|file| file.file_name() == expected_name)
+        .map(|file| vec![bin(file)])
+        .ok_or_else(|| miette!("Couldn't find any executables in the downloaded archive"))
+}
+
+/// Locates the executables a package declares in an archive.
+///
+/// Each path is looked up with the platform's executable suffix added too,
+/// in case the file doesn't exist without it.
+fn find_bins(files: &[ArchiveFile], bins: &[Bin]) -> Result<Vec<LayoutFile>> {
+    let mut found = Vec::new();
+    for bin in bins {
+        let name = bin.installed_name();
+        check_relative(name)?;
+        if name.contains('/') {
+            return Err(miette!("`{name}` isn't a valid name to install a binary as"));
+        }
+
+        let path = bin.path.trim_start_matches("./");
+        let file = files
+            .iter()
+            .find(|file| file.relative == path || file.relative == executable_name(path))
+            .ok_or_else(|| miette!("Couldn't find the binary `{}` in the downloaded archive", bin.path))?;
+        debug!("Found binary {name} at {}", file.relative);
+        found.push(LayoutFile {
+            source: file.source.clone(),
+            kind: FileKind::Bin,
+            name: executable_name(name),
+        });
+    }
+    Ok(found)
+}
+
+/// The names some files are installed as instead of their own.
+struct Renames(Vec<(GlobMatcher, String)>);
+
+impl Renames {
+    fn new(layout: &Layout) -> Result<Self> {
+        let mut renames = Vec::new();
+        for (pattern, name) in &layout.renames {
+            check_relative(name)?;
+            renames.push((Glob::new(pattern).into_diagnostic()?.compile_matcher(), name.clone()));
+        }
+        Ok(Self(renames))
+    }
+
+    /// The name to install `file` as, which is `default` unless it's
+    /// renamed.
+    fn name(&self, file: &ArchiveFile, default: &str) -> Result<String> {
+        let name = match self.0.iter().find(|(glob, _)| glob.is_match(&file.relative)) {
+            Some((_, name)) => name,
+            None => default,
+        };
+        check_relative(name)?;
+        Ok(name.to_string())
+    }
+}
+
+/// Recognizes the man pages and shell completions that archives commonly
+/// ship, returning what to install them as.
+///
+/// Conventions are only used for the kinds of file the package's layout
+/// doesn't already have globs for.
+fn by_convention(file: &ArchiveFile, layout: &Layout) -> Option<(FileKind, String)> {
+    let name = file.file_name();
+    // Completions are too easily confused with other scripts, so they're only
+    // picked up from directories like `completions/`, `complete/` or
+    // `autocomplete/`.
+    let in_completions_dir = file
+        .relative
+        .split('/')
+        .rev()
+        .skip(1)
+        .any(|dir| dir.to_lowercase().contains("complet"));
+
+    if layout.man_pages.is_empty() && is_man_page(file) {
+        return Some((
+            FileKind::ManPage {
+                section: man_section(name),
+            },
+            name.to_string(),
+        ));
+    }
+
+    let completions = &layout.completions;
+    if completions.bash.is_empty() && in_completions_dir {
+        // bash-completion looks completions up by the command's name.
+        if let Some(command) = name
+            .strip_suffix(".bash")
+            .or_else(|| name.strip_suffix(".bash-completion"))
+        {
+            return Some((FileKind::Completion(Shell::Bash), command.to_string()));
+        }
+    }
+    if completions.zsh.is_empty() {
+        if name.starts_with('_') && !name.contains('.') && (in_completions_dir || is_compdef(&file.source)) {
+            return Some((FileKind::Completion(Shell::Zsh), name.to_string()));
+        }
+        if let Some(command) = name.strip_suffix(".zsh").filter(|_| in_completions_dir) {
+            return Some((FileKind::Completion(Shell::Zsh), format!("_{command}")));
+        }
+    }
+    if completions.fish.is_empty() && in_completions_dir && name.ends_with(".fish") {
+        return Some((FileKind::Completion(Shell::Fish), name.to_string()));
+    }
+
+    None
+}
+
+/// Whether `file` is a man page. It has to be named like one, and either
+/// be in a directory like `man/`, `man1/` or `doc/`, or actually be roff,
+/// since plenty of other files are named like man pages too.
+fn is_man_page(file: &ArchiveFile) -> bool {
+    let in_man_dir = file.relative.split('/').rev().skip(1).any(|dir| {
+        let dir = dir.to_lowercase();
+        dir == "doc"
+            || dir
+                .strip_prefix("man")
+                .is_some_and(|section| section.chars().next().is_none_or(|c| c.is_ascii_digit()))
+    });
+    has_man_page_name(file.file_name()) && (in_man_dir || is_roff(&file.source))
+}
+
+/// Whether `file_name` is named like a man page, such as `rg.1` or
+/// `Foo::Bar.3pm.gz`.
+fn has_man_page_name(file_name: &str) -> bool {
+    let file_name = file_name.strip_suffix(".gz").unwrap_or(file_name);
+    let Some((stem, extension)) = file_name.rsplit_once('.') else {
+        return false;
+    };
+    let mut chars = extension.chars();
+    // A stem ending in a digit is more likely a version number, as in
+    // `tool-1.2`, and one ending in `.so` a shared library, as in
+    // `libfoo.so.1`.
+    !stem.is_empty()
+        && !stem.ends_with(|c: char| c.is_ascii_digit())
+        && !stem.ends_with(".so")
+        && chars.next().is_some_and(|section| matches!(section, '1'..='9'))
+        && chars.all(|c| c.is_ascii_lowercase())
+}
+
+/// Whether the file at `path`, which may be gzipped, starts like roff
+/// source does: with a request like `.TH` or `.Dd`, or a comment.
+fn is_roff(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut reader: Box<dyn Read> = if path.extension().is_some_and(|extension| extension == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut start = [0; 3];
+    reader.read_exact(&mut start).is_ok() && matches!(&start, b".TH" | b".Dd" | b".\\\"" | b"'\\\"")
+}
+
+/// Whether the file at `path` is a zsh completion function, which always
+/// starts with a `#compdef` line.
+fn is_compdef(path: &Path) -> bool {
+    let mut start = [0; 8];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut start))
+        .is_ok_and(|()| &start == b"#compdef")
+}
+
+/// Makes sure a file can't be installed outside of the directory it's meant
+/// to go in.
+fn check_relative(name: &str) -> Result<()> {
+    let path = Path::new(name);
+    if name.is_empty()
+        || name.contains('\\')
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(miette!("`{name}` isn't a valid name to install a file as"));
+    }
+    Ok(())
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).into_diagnostic()?);
+    }
+    builder.build().into_diagnostic()
+}
+
+/// Works out a man page's section from its file name, such as `1` for
+/// `rg.1` or `3` for `Foo::Bar.3pm.gz`, falling back to section 1.
+fn man_section(file_name: &str) -> char {
+    let file_name = file_name.strip_suffix(".gz").unwrap_or(file_name);
+    file_name
+        .rsplit_once('.')
+        .and_then(|(_, extension)| extension.chars().next())
+        .filter(|section| section.is_ascii_digit())
+        .unwrap_or('1')
+}
+
+/// Adds the platform's executable suffix (`.exe` on Windows) to `name` if it
+/// doesn't already have it.
+pub fn executable_name(name: &str) -> String {
+    if name.ends_with(env::consts::EXE_SUFFIX) {
+        name.to_string()
+    } else {
+        format!("{name}{}", env::consts::EXE_SUFFIX)
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exe"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    /// Writes `files`, given as `(path, contents)` pairs, into a temporary
+    /// directory standing in for an extracted archive.
+    fn extracted(files: &[(&str, &[u8])]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn relative_paths(dir: &Path, strip_components: Option<usize>) -> Vec<String> {
+        list_files(dir, strip_components)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.relative)
+            .collect()
+    }
+
+    fn archive_file(dir: &Path, relative: &str) -> ArchiveFile {
+        ArchiveFile {
+            source: dir.join(relative),
+            relative: relative.to_string(),
+        }
+    }
+
+    #[test]
+    fn leading_directories_are_stripped() {
+        let dir = extracted(&[
+            ("tool-1.0/bin/tool", b""),
+            ("tool-1.0/doc/tool.1", b""),
+            ("tool-1.0/README", b""),
+        ]);
+        assert_eq!(relative_paths(dir.path(), None), ["README", "bin/tool", "doc/tool.1"]);
+        assert_eq!(
+            relative_paths(dir.path(), Some(0)),
+            ["tool-1.0/README", "tool-1.0/bin/tool", "tool-1.0/doc/tool.1"]
+        );
+        // Files that would be stripped away entirely are skipped.
+        assert_eq!(relative_paths(dir.path(), Some(2)), ["tool", "tool.1"]);
+        assert!(relative_paths(dir.path(), Some(3)).is_empty());
+
+        // Without a single wrapping directory, nothing is stripped.
+        let dir = extracted(&[("tool", b""), ("doc/tool.1", b"")]);
+        assert_eq!(relative_paths(dir.path(), None), ["doc/tool.1", "tool"]);
+    }
+
+    #[test]
+    fn man_page_names_are_recognized() {
+        for name in ["rg.1", "rg.1.gz", "Foo::Bar.3pm.gz", "tool-daemon.8"] {
+            assert!(has_man_page_name(name), "{name}");
+        }
+        for name in [
+            "libfoo.so.1",
+            "libLLVM.so.15",
+            "tool-1.2",
+            "README",
+            ".1",
+            "tool.0",
+            "tool.1X",
+            "tool.txt",
+        ] {
+            assert!(!has_man_page_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn man_pages_need_a_man_directory_or_roff_contents() {
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(b".TH RG 1\n").unwrap();
+        let gzipped = gzipped.finish().unwrap();
+        let dir = extracted(&[
+            ("doc/rg.1", b"not roff"),
+            ("man/man1/rg.1.gz", b"not roff either"),
+            ("share/man1/rg.1", b""),
+            ("rg.1", b".TH RG 1\n"),
+            ("tool.1", b".\\\" generated by a tool\n.TH TOOL 1\n"),
+            ("mdoc.1", b".Dd January 1, 2023\n"),
+            ("rg.1.gz", &gzipped),
+            ("lib/libfoo.so.1", b"\x7fELF"),
+            ("notes.1", b"Release notes"),
+        ]);
+        for relative in [
+            "doc/rg.1",
+            "man/man1/rg.1.gz",
+            "share/man1/rg.1",
+            "rg.1",
+            "tool.1",
+            "mdoc.1",
+            "rg.1.gz",
+        ] {
+            assert!(is_man_page(&archive_file(dir.path(), relative)), "{relative}");
+        }
+        for relative in ["lib/libfoo.so.1", "notes.1"] {
+            assert!(!is_man_page(&archive_file(dir.path(), relative)), "{relative}");
+        }
+    }
+
+    #[test]
+    fn conventional_files_are_recognized() {
+        let dir = extracted(&[
+            ("doc/rg.1", b""),
+            ("doc/rg.3pm.gz", b""),
+            ("complete/rg.bash", b""),
+            ("complete/_rg", b""),
+            ("completions/rg.zsh", b""),
+            ("completions/rg.fish", b""),
+            ("_tool", b"#compdef tool\n"),
+            ("_other", b"#!/bin/sh\n"),
+            ("scripts/install.bash", b""),
+            ("lib/libfoo.so.1", b""),
+        ]);
+        let layout = Layout::default();
+        let kind = |relative: &str| by_convention(&archive_file(dir.path(), relative), &layout);
+
+        assert_eq!(
+            kind("doc/rg.1"),
+            Some((FileKind::ManPage { section: '1' }, "rg.1".to_string()))
+        );
+        assert_eq!(
+            kind("doc/rg.3pm.gz"),
+            Some((FileKind::ManPage { section: '3' }, "rg.3pm.gz".to_string()))
+        );
+        assert_eq!(
+            kind("complete/rg.bash"),
+            Some((FileKind::Completion(Shell::Bash), "rg".to_string()))
+        );
+        assert_eq!(
+            kind("complete/_rg"),
+            Some((FileKind::Completion(Shell::Zsh), "_rg".to_string()))
+        );
+        assert_eq!(
+            kind("completions/rg.zsh"),
+            Some((FileKind::Completion(Shell::Zsh), "_rg".to_string()))
+        );
+        assert_eq!(
+            kind("completions/rg.fish"),
+            Some((FileKind::Completion(Shell::Fish), "rg.fish".to_string()))
+        );
+        assert_eq!(
+            kind("_tool"),
+            Some((FileKind::Completion(Shell::Zsh), "_tool".to_string()))
+        );
+        assert_eq!(kind("_other"), None);
+        assert_eq!(kind("scripts/install.bash"), None);
+        assert_eq!(kind("lib/libfoo.so.1"), None);
+
+        // Kinds the layout has globs for aren't guessed.
+        let layout = Layout {
+            man_pages: vec!["man/*".to_string()],
+            ..Layout::default()
+        };
+        assert_eq!(by_convention(&archive_file(dir.path(), "doc/rg.1"), &layout), None);
+    }
+}
@@ -1,49 +1,420 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
 use colored::Colorize;
 use dialoguer::Confirm;
-use index_client::IndexClient;
+use index_client::{metadata::PackageMetadata, resolver::VersionSpec};
 use log::{debug, info};
 use miette::{miette, IntoDiagnostic, Result};
+use octocrab::models::repos::Release;
+use tokio::{fs, task};
 
+mod checksum;
+mod choices;
+mod download;
+mod extract;
+mod layout;
 mod picker;
-use picker::AssetPicker;
+mod plan;
+mod scoring;
+mod shell_setup;
+mod signature;
+use choices::AssetChoices;
+use extract::{ArchiveFormat, Compression};
+use layout::{FileKind, LayoutFile};
+pub use plan::InstallPlan;
+pub use scoring::Platform;
+use signature::SignedFile;
 
-use crate::{config::get_config, defaults::theme};
+use crate::{
+    cache::DownloadCache,
+    config::get_config,
+    defaults::theme,
+    digest::sha256_file,
+    dirs::get_project_dirs,
+    project::{LockError, LockedPackage, Project, LOCKFILE},
+    receipts::{InstalledFile, Receipt, Receipts},
+    versions::{self, InstalledVersions},
+};
 
 pub struct Install;
 
 impl Install {
-    pub async fn execute(package: &str, _dry_run: &bool) -> Result<()> {
-        let config = get_config()?;
-        let picker = AssetPicker::new();
+    pub async fn execute(
+        package: &str,
+        version: &Option<String>,
+        pre: &bool,
+        dry_run: &bool,
+        yes: &bool,
+    ) -> Result<()> {
+        let (package, version) = match (package.split_once('@'), version) {
+            (Some((_, tag)), Some(version)) if tag != version => {
+                return Err(miette!(
+                    "Conflicting versions `{tag}` and `{version}` were requested for {}",
+                    package.bold()
+                ))
+            }
+            (Some((package, tag)), _) => (package, Some(tag)),
+            (None, version) => (package, version.as_deref()),
+        };
 
-        let pat = config.get_pat()?;
-        let index_client = IndexClient::new(&config.index, env!("CARGO_PKG_VERSION"), pat.clone()).await?;
+        let config = get_config()?;
+        let indexes = config.indexes().await?;
 
         info!("Fetching package metadata for package {}.", package.bold());
-        let package_metadata = index_client.get_package(package).await?;
+        let package_metadata = indexes.get_package(package).await?;
         debug!("Fetched package metadata: {:#?}", package_metadata);
 
-        let release = package_metadata.get_latest_release().await?;
+        let release = match version {
+            Some(version) => {
+                package_metadata
+                    .get_release_matching(&VersionSpec::parse(version), *pre)
+                    .await?
+            }
+            None => package_metadata.get_latest_release().await?,
+        };
+
+        let receipts = Receipts::open()?;
+        if let Some(receipt) = receipts.get(&package_metadata.name).await? {
+            check_same_index(&package_metadata, &receipt)?;
+            info!(
+                "{} {} is already installed, it will be replaced.",
+                package_metadata.pretty_name.bold(),
+                receipt.tag.bold()
+            );
+        }
+
+        let plan = InstallPlan::new(&package_metadata, release).await?;
+        plan.print(&package_metadata);
+        if *dry_run {
+            return Ok(());
+        }
+
+        Self::confirm(&format!("Install {} {}?", package_metadata.pretty_name, plan.tag), *yes)?;
+        Self::apply(&package_metadata, plan, &receipts).await?;
+
+        Ok(())
+    }
+
+    /// Installs the packages pinned by the current project's `snowdrop.lock`
+    /// exactly as they're pinned, or just `package` if one is given.
+    pub async fn execute_locked(package: Option<&str>, dry_run: &bool, yes: &bool) -> Result<()> {
+        let project = Project::find().await?;
+        let lockfile = project.read_lockfile().await?.ok_or(LockError::NoLockfile)?;
+        let locked = match package {
+            Some(package) => vec![lockfile
+                .get(package)
+                .ok_or_else(|| LockError::PackageNotLocked(package.to_string()))?],
+            None => lockfile.packages.iter().collect(),
+        };
+        if locked.is_empty() {
+            println!("{}", format!("{LOCKFILE} doesn't pin any packages.").bold());
+            return Ok(());
+        }
+
+        let indexes = get_config()?.indexes().await?;
+        let mut plans = Vec::new();
+        for locked in locked {
+            info!("Fetching package metadata for package {}.", locked.name.bold());
+            let package_metadata = indexes.get_package(&locked.name).await?;
+            let plan = Self::plan_locked(&package_metadata, locked).await?;
+            plan.print(&package_metadata);
+            plans.push((package_metadata, plan));
+        }
+        if *dry_run {
+            return Ok(());
+        }
+
+        Self::confirm(&format!("Install {} packages from {LOCKFILE}?", plans.len()), *yes)?;
+        let receipts = Receipts::open()?;
+        for (package_metadata, plan) in plans {
+            Self::apply(&package_metadata, plan, &receipts).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Asks the user to confirm before going ahead, unless `yes` is set.
+    pub fn confirm(prompt: &str, yes: bool) -> Result<()> {
+        if yes {
+            return Ok(());
+        }
 
-        let should_install = Confirm::with_theme(&theme())
-            .with_prompt(format!(
-                "Install {}?",
-                release.name.unwrap_or_else(|| "(version unspecified)".to_string())
-            ))
+        let confirmed = Confirm::with_theme(&theme())
+            .with_prompt(prompt)
             .default(false)
             .interact()
             .into_diagnostic()?;
-
-        if !should_install {
+        if !confirmed {
             return Err(miette!("User aborted operation"));
         }
+        Ok(())
+    }
 
-        dbg!(
-            picker
-                .choose_asset(release.assets, package_metadata.naming_scheme)?
-                .name
-        );
+    /// Plans installing the release and asset of a package pinned by a
+    /// lockfile for the host platform.
+    pub async fn plan_locked(package_metadata: &PackageMetadata, locked: &LockedPackage) -> Result<InstallPlan> {
+        let platform = Platform::host();
+        let asset = locked
+            .asset_for(&platform.triple)
+            .ok_or_else(|| LockError::PlatformNotLocked {
+                package: locked.name.clone(),
+                platform: platform.triple.clone(),
+            })?;
+        let release = package_metadata.get_release(&locked.tag).await?;
+        InstallPlan::locked(package_metadata, release, asset).await
+    }
 
-        Ok(())
+    /// Downloads and installs `release` of a package, then records it in
+    /// `receipts`.
+    pub async fn install_release(
+        package_metadata: &PackageMetadata,
+        release: Release,
+        receipts: &Receipts,
+    ) -> Result<Receipt> {
+        let plan = InstallPlan::new(package_metadata, release).await?;
+        Self::apply(package_metadata, plan, receipts).await
+    }
+
+    /// Carries out an install plan, then records the result in `receipts`.
+    pub async fn apply(package_metadata: &PackageMetadata, plan: InstallPlan, receipts: &Receipts) -> Result<Receipt> {
+        if let Some(installed) = receipts.get(&package_metadata.name).await? {
+            check_same_index(package_metadata, &installed)?;
+        }
+
+        let cache_dir = get_project_dirs()?.cache_dir();
+        fs::create_dir_all(cache_dir).await.into_diagnostic()?;
+        let staging = tempfile::Builder::new()
+            .prefix("staging-")
+            .tempdir_in(cache_dir)
+            .into_diagnostic()?;
+
+        let (asset_path, asset_sha256) = Self::fetch(&plan, staging.path()).await?;
+
+        let layout_files = match ArchiveFormat::detect(&asset_path)? {
+            Some(format) => {
+                let extracted = staging.path().join("extracted");
+                let (asset_path, extracted_dir) = (asset_path.clone(), extracted.clone());
+                task::spawn_blocking(move || format.extract(&asset_path, &extracted_dir))
+                    .await
+                    .into_diagnostic()??;
+
+                layout::collect(&extracted, package_metadata)?
+            }
+            None => {
+                // Executables are often released compressed on their own.
+                let asset_path = match Compression::detect(&asset_path)? {
+                    Some(compression) => {
+                        let decompressed = staging.path().join("decompressed");
+                        let (asset_path, output) = (asset_path.clone(), decompressed.clone());
+                        task::spawn_blocking(move || compression.decompress(&asset_path, &output))
+                            .await
+                            .into_diagnostic()??;
+                        decompressed
+                    }
+                    None => asset_path,
+                };
+                let name = match package_metadata.bins.as_slice() {
+                    [] => package_metadata.name.as_str(),
+                    [bin] => bin.installed_name(),
+                    bins => {
+                        return Err(miette!(
+                            "The release asset is a single executable, but {} declares {} binaries",
+                            package_metadata.pretty_name.bold(),
+                            bins.len()
+                        ))
+                    }
+                };
+                vec![LayoutFile {
+                    source: asset_path,
+                    kind: FileKind::Bin,
+                    name: layout::executable_name(name),
+                }]
+            }
+        };
+
+        let asset = plan.asset;
+        let destinations = layout_files
+            .iter()
+            .map(|file| file.destination(&package_metadata.name))
+            .collect::<Result<Vec<_>>>()?;
+        check_conflicts(package_metadata, receipts, &destinations).await?;
+
+        // Each version is installed into its own directory, laid out like the
+        // data directory. It's staged next to where it ends up and only moved
+        // there once every file is in, and its files are only linked into
+        // place after that.
+        let data_dir = get_project_dirs()?.data_dir();
+        let versions = InstalledVersions::open(&package_metadata.name)?;
+        let version_dir = versions.dir(&plan.tag);
+        let version_staging = versions.staging_dir().await?;
+
+        let mut files = Vec::new();
+        for (file, destination) in layout_files.iter().zip(destinations) {
+            let relative = destination.strip_prefix(data_dir).into_diagnostic()?;
+            let staged = version_staging.path().join(relative);
+            if let Some(parent) = staged.parent() {
+                fs::create_dir_all(parent).await.into_diagnostic()?;
+            }
+            fs::copy(&file.source, &staged).await.into_diagnostic()?;
+            if file.kind == FileKind::Bin {
+                download::make_executable(&staged).await?;
+                info!(
+                    "Installed {} to {}.",
+                    file.name.bold(),
+                    destination.display().to_string().bold()
+                );
+            } else {
+                debug!("Installed {} to {}", file.name, destination.display());
+            }
+
+            files.push(InstalledFile {
+                sha256: sha256_file(&staged).await?,
+                target: Some(version_dir.join(relative)),
+                path: destination,
+            });
+        }
+        let supporting = layout_files.iter().filter(|file| file.kind != FileKind::Bin).count();
+        if supporting > 0 {
+            info!(
+                "Installed {supporting} supporting files for {}.",
+                package_metadata.pretty_name.bold()
+            );
+        }
+
+        let receipt = Receipt {
+            name: package_metadata.name.clone(),
+            index: Some(package_metadata.index.clone()),
+            repo: package_metadata.repo.clone(),
+            tag: plan.tag,
+            asset_name: asset.name,
+            asset_id: asset.id.0,
+            download_url: asset.browser_download_url.to_string(),
+            asset_sha256,
+            previous: Vec::new(),
+            files,
+        };
+        versions.install(version_staging, &receipt).await?;
+        let receipt = versions::activate(receipts, receipt, get_config()?.keep_versions).await?;
+        if plan.chosen_by_user {
+            AssetChoices::open()?.remember(&receipt.name, &receipt.tag, &receipt.asset_name)?;
+        }
+        shell_setup::print_once(&layout_files).await?;
+
+        Ok(receipt)
+    }
+
+    /// Gets the planned asset into `dir`, from the download cache if it's
+    /// there, and checks its signature, checksum and pinned digest.
+    ///
+    /// Returns the path of the asset and its SHA-256 digest.
+    pub async fn fetch(plan: &InstallPlan, dir: &Path) -> Result<(PathBuf, String)> {
+        let asset = &plan.asset;
+        let asset_path = dir.join(&asset.name);
+        let cache = DownloadCache::open()?;
+        let (asset_sha256, was_cached) = match cache.get(asset.id.0, plan.expected_sha256.as_deref()).await? {
+            Some(cached) => {
+                info!("Using cached download of {}.", asset.name.bold());
+                fs::copy(&cached.path, &asset_path).await.into_diagnostic()?;
+                (cached.sha256, true)
+            }
+            None => {
+                download::download_asset(asset, &asset_path).await?;
+                (sha256_file(&asset_path).await?, false)
+            }
+        };
+        if let Some(expected) = &plan.expected_sha256 {
+            if &asset_sha256 != expected {
+                return Err(LockError::DigestMismatch {
+                    asset_name: asset.name.clone(),
+                    expected: expected.clone(),
+                    actual: asset_sha256,
+                }
+                .into());
+            }
+        }
+
+        let checksum_path = match &plan.checksum_asset {
+            Some(checksum_asset) => {
+                let checksum_path = dir.join(&checksum_asset.name);
+                download::download_asset(checksum_asset, &checksum_path).await?;
+                Some(checksum_path)
+            }
+            None => None,
+        };
+
+        // Signatures are checked first, as a checksum file is only trustworthy
+        // once its signature has been.
+        if let Some(planned) = &plan.signature {
+            let signature_path = dir.join(&planned.asset.name);
+            download::download_asset(&planned.asset, &signature_path).await?;
+            let signed_path = match (planned.signs, &checksum_path) {
+                (SignedFile::ChecksumFile, Some(checksum_path)) => checksum_path,
+                _ => &asset_path,
+            };
+            signature::verify(&planned.key, signed_path, &signature_path).await?;
+        }
+        if let Some(checksum_path) = &checksum_path {
+            checksum::verify(checksum_path, &asset.name, &asset_path, plan.checksum_required).await?;
+        }
+
+        // Downloads are only cached once they've passed every check, so a
+        // bad one is never reused.
+        if !was_cached {
+            cache.insert(asset.id.0, &asset_path, &asset_sha256).await?;
+        }
+
+        Ok((asset_path, asset_sha256))
+    }
+}
+
+/// Makes sure a package isn't installed over a package of the same name from
+/// another index, as installed packages are only told apart by name.
+fn check_same_index(package_metadata: &PackageMetadata, installed: &Receipt) -> Result<()> {
+    match &installed.index {
+        Some(index) if index != &package_metadata.index => Err(miette!(
+            "{} is already installed from the `{index}` index, so it can't be replaced with the one from `{}`. \
+             Uninstall it first, or use `{index}/{}` to keep the one from `{index}`.",
+            package_metadata.name.bold(),
+            package_metadata.index,
+            package_metadata.name
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Makes sure no two files would be installed at the same place, and that
+/// none of them would overwrite a file from another package.
+async fn check_conflicts(
+    package_metadata: &PackageMetadata,
+    receipts: &Receipts,
+    destinations: &[PathBuf],
+) -> Result<()> {
+    let others = receipts
+        .list()
+        .await?
+        .into_iter()
+        .filter(|receipt| receipt.name != package_metadata.name)
+        .collect::<Vec<_>>();
+
+    let mut seen = HashSet::new();
+    for destination in destinations {
+        if !seen.insert(destination) {
+            return Err(miette!(
+                "More than one file would be installed at {}",
+                destination.display()
+            ));
+        }
+        if let Some(owner) = others.iter().find(|receipt| receipt.owns(destination)) {
+            return Err(miette!(
+                "Installing {} would overwrite {}, which belongs to {}",
+                package_metadata.pretty_name.bold(),
+                destination.display(),
+                owner.name.bold()
+            ));
+        }
     }
+    Ok(())
 }
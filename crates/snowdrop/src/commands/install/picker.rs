@@ -1,48 +1,195 @@
-use std::{collections::HashMap, env};
+use std::collections::HashMap;
 
+use dialoguer::{console, Select};
 use globset::Glob;
 use log::debug;
 use maplit::hashmap;
-use miette::{IntoDiagnostic, Result};
+use miette::{Diagnostic, IntoDiagnostic, Result};
 use octocrab::models::repos::Asset;
+use thiserror::Error;
 
-pub struct AssetPicker<'a> {
-    format_data: HashMap<&'a str, &'a str>,
+use super::{
+    choices::AssetChoices,
+    scoring::{self, Platform},
+};
+use crate::defaults::theme;
+
+/// A release asset chosen by the [`AssetPicker`], along with why it was
+/// chosen.
+pub struct PickedAsset {
+    pub asset: Asset,
+    pub reason: String,
+
+    /// Whether the user picked the asset, in which case the choice should be
+    /// remembered once the asset is installed.
+    pub chosen_by_user: bool,
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum AssetPickerError {
+    #[error("The release has no assets")]
+    NoAssets,
+
+    #[error("Couldn't pick a release asset for this platform automatically")]
+    #[diagnostic(help("Run Snowdrop interactively to choose one of the available assets: {available}"))]
+    NoMatch { available: String },
+
+    #[error("Couldn't pick a release asset for {platform} automatically")]
+    #[diagnostic(help("Give the package a naming scheme that matches one of the available assets: {available}"))]
+    PlatformUnmatched { platform: String, available: String },
 }
 
-impl AssetPicker<'_> {
+pub struct AssetPicker {
+    platform: Platform,
+    format_data: HashMap<&'static str, String>,
+}
+
+impl AssetPicker {
     pub fn new() -> Self {
+        Self::for_platform(Platform::host())
+    }
+
+    /// Creates a picker that chooses assets for `platform` instead of the
+    /// host.
+    ///
+    /// Remembered choices and prompting only apply to the host, so for any
+    /// other platform the asset has to be picked automatically.
+    pub fn for_platform(platform: Platform) -> Self {
         Self {
             format_data: hashmap! {
-                "{{llvm_triple}}" => current_platform::CURRENT_PLATFORM,
-                "{{basic_platform}}" => env::consts::OS,
-                "{{basic_platform_osx}}" => match env::consts::OS {
+                "{{llvm_triple}}" => platform.triple.clone(),
+                "{{basic_platform}}" => platform.os.to_string(),
+                "{{basic_platform_osx}}" => match platform.os {
                     "macos" => "osx",
-                    _ => env::consts::OS
-                }
+                    os => os
+                }.to_string()
             },
+            platform,
         }
     }
 
-    pub fn choose_asset(&self, assets: Vec<Asset>, mut naming_scheme: String) -> Result<Asset> {
-        for (key, value) in self.format_data.iter() {
-            // TODO: Find a better way to do this!
-            naming_scheme = naming_scheme.replace(key, value)
+    /// Chooses the asset to install for `package` from the assets of the
+    /// release tagged `tag`.
+    ///
+    /// If there's no naming scheme or nothing matches it, a previously
+    /// remembered choice is used, then the asset that best fits the
+    /// platform. If there's no clear winner, the user is asked to pick one.
+    pub fn choose_asset(
+        &self,
+        package: &str,
+        tag: &str,
+        assets: Vec<Asset>,
+        naming_scheme: Option<String>,
+    ) -> Result<PickedAsset> {
+        if assets.is_empty() {
+            return Err(AssetPickerError::NoAssets.into());
         }
-        let glob = Glob::new(&naming_scheme).into_diagnostic()?.compile_matcher();
-        for asset in assets {
-            if glob.is_match(&asset.name) {
+
+        if let Some(mut naming_scheme) = naming_scheme {
+            for (key, value) in self.format_data.iter() {
+                // TODO: Find a better way to do this!
+                naming_scheme = naming_scheme.replace(key, value)
+            }
+            let glob = Glob::new(&naming_scheme).into_diagnostic()?.compile_matcher();
+            if let Some(index) = assets.iter().position(|asset| glob.is_match(&asset.name)) {
+                let asset = assets.into_iter().nth(index).unwrap();
                 debug!("Found a match: {}", asset.name);
-                return Ok(asset);
+                return Ok(PickedAsset {
+                    asset,
+                    reason: format!("matched the naming scheme `{naming_scheme}`"),
+                    chosen_by_user: false,
+                });
             }
+            debug!("Nothing matched the naming scheme `{naming_scheme}`");
         }
-        debug!("Didn't find a match, prompting user...");
 
-        todo!()
+        let is_host = self.platform.is_host();
+        let choices = AssetChoices::open()?;
+        if let Some(remembered) = choices.get(package, tag).filter(|_| is_host) {
+            if let Some(index) = assets.iter().position(|asset| asset.name == remembered) {
+                let asset = assets.into_iter().nth(index).unwrap();
+                debug!("Using remembered choice: {}", asset.name);
+                return Ok(PickedAsset {
+                    asset,
+                    reason: "was chosen for a previous release".to_string(),
+                    chosen_by_user: false,
+                });
+            }
+        }
+
+        let available = assets
+            .iter()
+            .map(|asset| asset.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut ranked = scoring::rank(assets, &self.platform);
+        debug!(
+            "Ranked assets: {:?}",
+            ranked
+                .iter()
+                .map(|(asset, _, score)| (&asset.name, score))
+                .collect::<Vec<_>>()
+        );
+
+        // Only pick automatically if the best asset names both the OS and
+        // architecture, and nothing else scored as well.
+        let is_clear_winner = match ranked.as_slice() {
+            [(_, tokens, best), rest @ ..] => {
+                tokens.os.is_some()
+                    && tokens.arch.is_some()
+                    && rest.first().is_none_or(|(_, _, second)| best > second)
+            }
+            [] => false,
+        };
+        if is_clear_winner {
+            let (asset, tokens, _) = ranked.remove(0);
+            debug!("Picked the best fit for {}: {}", self.platform.triple, asset.name);
+            let hints = [tokens.os, tokens.arch, tokens.libc]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", ");
+            let platform = if is_host {
+                "this platform"
+            } else {
+                &self.platform.triple
+            };
+            return Ok(PickedAsset {
+                asset,
+                reason: format!("was the best fit for {platform} ({hints})"),
+                chosen_by_user: false,
+            });
+        }
+
+        if !is_host {
+            return Err(AssetPickerError::PlatformUnmatched {
+                platform: self.platform.triple.clone(),
+                available,
+            }
+            .into());
+        }
+        if ranked.is_empty() || !console::user_attended() {
+            return Err(AssetPickerError::NoMatch { available }.into());
+        }
+        debug!("Didn't find a clear match, prompting user...");
+
+        let index = Select::with_theme(&theme())
+            .with_prompt("Couldn't pick an asset automatically, which one should be installed?")
+            .items(&ranked.iter().map(|(asset, ..)| asset.name.as_str()).collect::<Vec<_>>())
+            .default(0)
+            .interact()
+            .into_diagnostic()?;
+        let (asset, ..) = ranked.swap_remove(index);
+
+        Ok(PickedAsset {
+            asset,
+            reason: "was chosen by you".to_string(),
+            chosen_by_user: true,
+        })
     }
 }
 
-impl Default for AssetPicker<'_> {
+impl Default for AssetPicker {
     fn default() -> Self {
         Self::new()
     }
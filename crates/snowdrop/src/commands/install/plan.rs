@@ -0,0 +1,259 @@
+use std::path::PathBuf;
+
+use colored::Colorize;
+use index_client::metadata::PackageMetadata;
+use miette::{IntoDiagnostic, Result};
+use octocrab::models::repos::{Asset, Release};
+use tokio::fs;
+
+use super::{
+    checksum,
+    extract::ArchiveFormat,
+    layout,
+    picker::{AssetPicker, PickedAsset},
+    scoring::Platform,
+    signature::{self, PublicKey, SignedFile},
+};
+use crate::{
+    dirs::get_bin_dir,
+    project::{LockError, LockedAsset},
+    receipts::Receipts,
+    units::format_size,
+};
+
+/// Everything an install is going to do, worked out before anything is
+/// downloaded or written.
+pub struct InstallPlan {
+    pub tag: String,
+    pub release_name: Option<String>,
+    pub asset: Asset,
+
+    /// Why the asset was chosen.
+    pub reason: String,
+
+    /// Whether the user picked the asset, so the choice is remembered once
+    /// it's installed.
+    pub chosen_by_user: bool,
+
+    /// The release asset holding the asset's checksum, if there is one.
+    pub checksum_asset: Option<Asset>,
+
+    /// Whether the checksum asset was named by the package, in which case it
+    /// must list the asset.
+    pub checksum_required: bool,
+
+    /// The signature to check before anything is extracted, if the package
+    /// is signed.
+    pub signature: Option<PlannedSignature>,
+
+    /// The SHA-256 digest the asset must have, if it's pinned by a lockfile.
+    pub expected_sha256: Option<String>,
+
+    /// Whether the asset is an archive that will need extracting.
+    pub archive_format: Option<ArchiveFormat>,
+
+    /// The files that are expected to be written.
+    pub files: Vec<PlannedFile>,
+}
+
+pub struct PlannedSignature {
+    pub key: PublicKey,
+    pub asset: Asset,
+    pub signs: SignedFile,
+}
+
+pub struct PlannedFile {
+    pub path: PathBuf,
+
+    /// Whether a file already exists at `path`.
+    pub overwrites: bool,
+
+    /// The other installed package the file at `path` belongs to, if any.
+    pub owner: Option<String>,
+}
+
+impl InstallPlan {
+    pub async fn new(package_metadata: &PackageMetadata, release: Release) -> Result<Self> {
+        Self::for_platform(package_metadata, release, Platform::host()).await
+    }
+
+    /// Plans installing `release` on `platform`, which doesn't have to be the
+    /// host.
+    pub async fn for_platform(
+        package_metadata: &PackageMetadata,
+        release: Release,
+        platform: Platform,
+    ) -> Result<Self> {
+        let picked = AssetPicker::for_platform(platform).choose_asset(
+            &package_metadata.name,
+            &release.tag_name,
+            release.assets.clone(),
+            package_metadata.naming_scheme.clone(),
+        )?;
+        Self::with_asset(package_metadata, release, picked).await
+    }
+
+    /// Plans installing exactly the asset pinned by a lockfile, which has to
+    /// still have the pinned digest once it's downloaded.
+    pub async fn locked(package_metadata: &PackageMetadata, release: Release, locked: &LockedAsset) -> Result<Self> {
+        let Some(asset) = release.assets.iter().find(|asset| asset.id.0 == locked.id) else {
+            return Err(LockError::AssetGone {
+                asset_name: locked.name.clone(),
+                tag: release.tag_name,
+            }
+            .into());
+        };
+        let picked = PickedAsset {
+            asset: asset.clone(),
+            reason: "is pinned by snowdrop.lock".to_string(),
+            chosen_by_user: false,
+        };
+
+        let mut plan = Self::with_asset(package_metadata, release, picked).await?;
+        plan.expected_sha256 = Some(locked.sha256.clone());
+        Ok(plan)
+    }
+
+    async fn with_asset(package_metadata: &PackageMetadata, release: Release, picked: PickedAsset) -> Result<Self> {
+        let assets = release.assets;
+        let checksum_asset =
+            checksum::find_checksum_asset(&assets, &picked.asset.name, package_metadata.checksum_asset.as_deref())?
+                .cloned();
+
+        let signature = match &package_metadata.public_key {
+            Some(public_key) => {
+                let key = PublicKey::parse(public_key)?;
+                let (asset, signs) = signature::find_signature_asset(
+                    &assets,
+                    &key,
+                    &picked.asset.name,
+                    checksum_asset.as_ref().map(|asset| asset.name.as_str()),
+                    package_metadata.signature_asset.as_deref(),
+                )?;
+                Some(PlannedSignature { key, asset, signs })
+            }
+            None => None,
+        };
+        // If only the checksum file is signed, it's what vouches for the
+        // asset, so it has to list it.
+        let checksum_required = package_metadata.checksum_asset.is_some()
+            || signature
+                .as_ref()
+                .is_some_and(|signature| signature.signs == SignedFile::ChecksumFile);
+        let archive_format = ArchiveFormat::from_file_name(&picked.asset.name);
+
+        // The contents of an archive aren't known until it's extracted, so
+        // unless the package lists its binaries, assume it contains an
+        // executable named after the package.
+        let layout_bins = package_metadata
+            .layout
+            .as_ref()
+            .is_some_and(|layout| !layout.bins.is_empty());
+        let names = if layout_bins {
+            Vec::new()
+        } else if package_metadata.bins.is_empty() {
+            vec![layout::executable_name(&package_metadata.name)]
+        } else {
+            package_metadata
+                .bins
+                .iter()
+                .map(|bin| layout::executable_name(bin.installed_name()))
+                .collect()
+        };
+        let bin_dir = get_bin_dir()?;
+        let installed = Receipts::open()?.list().await?;
+        let mut files = Vec::new();
+        for name in names {
+            let path = bin_dir.join(name);
+            files.push(PlannedFile {
+                overwrites: fs::try_exists(&path).await.into_diagnostic()?,
+                owner: installed
+                    .iter()
+                    .find(|receipt| receipt.name != package_metadata.name && receipt.owns(&path))
+                    .map(|receipt| receipt.name.clone()),
+                path,
+            });
+        }
+
+        Ok(Self {
+            tag: release.tag_name,
+            release_name: release.name,
+            asset: picked.asset,
+            reason: picked.reason,
+            chosen_by_user: picked.chosen_by_user,
+            checksum_asset,
+            checksum_required,
+            signature,
+            expected_sha256: None,
+            archive_format,
+            files,
+        })
+    }
+
+    pub fn print(&self, package_metadata: &PackageMetadata) {
+        println!(
+            "{}",
+            format!("Install plan for {}:", package_metadata.pretty_name).bold()
+        );
+        match &self.release_name {
+            Some(name) if name != &self.tag => println!("  Release: {} ({name})", self.tag.bold()),
+            _ => println!("  Release: {}", self.tag.bold()),
+        }
+        println!(
+            "  Asset:   {} ({})",
+            self.asset.name.bold(),
+            format_size(self.asset.size.max(0) as u64)
+        );
+        println!("           {}", self.reason.dimmed());
+        match &self.checksum_asset {
+            Some(checksum_asset) => println!(
+                "           {}",
+                format!("verified against {}", checksum_asset.name).dimmed()
+            ),
+            None => println!("           {}", "no published checksum to verify against".yellow()),
+        }
+        if let Some(signature) = &self.signature {
+            let signed = match signature.signs {
+                SignedFile::Asset => "asset",
+                SignedFile::ChecksumFile => "checksum file",
+            };
+            println!(
+                "           {}",
+                format!("{signed} signature verified against {}", signature.asset.name).dimmed()
+            );
+        }
+        if let Some(sha256) = &self.expected_sha256 {
+            println!("           {}", format!("pinned to SHA-256 {sha256}").dimmed());
+        }
+        if let Some(format) = self.archive_format {
+            println!("           {}", format!("will be extracted as {format:?}").dimmed());
+        }
+
+        println!("  Files:");
+        for file in &self.files {
+            if let Some(owner) = &file.owner {
+                println!(
+                    "    {} {} {}",
+                    "!".red().bold(),
+                    file.path.display(),
+                    format!("(belongs to {owner})").red()
+                );
+            } else if file.overwrites {
+                println!(
+                    "    {} {} {}",
+                    "~".yellow().bold(),
+                    file.path.display(),
+                    "(overwrites an existing file)".yellow()
+                );
+            } else {
+                println!("    {} {}", "+".green().bold(), file.path.display());
+            }
+        }
+        if package_metadata.layout.is_some() {
+            println!(
+                "    {}",
+                "...and whatever else the package's layout matches in the archive".dimmed()
+            );
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::env;
+
+use octocrab::models::repos::Asset;
+
+use super::extract::ArchiveFormat;
+
+/// The platform hints found in an asset's name.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AssetTokens {
+    /// The operating system, using the same names as [`env::consts::OS`].
+    pub os: Option<&'static str>,
+
+    /// The architecture, using the same names as [`env::consts::ARCH`].
+    /// Universal macOS binaries are reported as `universal`.
+    pub arch: Option<&'static str>,
+
+    /// The C library or ABI the asset was built against (`musl`, `gnu` or
+    /// `msvc`).
+    pub libc: Option<&'static str>,
+
+    pub archive_format: Option<ArchiveFormat>,
+
+    /// Set if the asset is something that can never be installed, such as a
+    /// checksum file, a signature or a source tarball.
+    pub unusable: Option<&'static str>,
+}
+
+const OS_ALIASES: &[(&str, &[&str])] = &[
+    ("linux", &["linux"]),
+    ("macos", &["darwin", "macos", "osx", "apple", "mac"]),
+    ("windows", &["windows", "win64", "win32", "win"]),
+    ("freebsd", &["freebsd"]),
+    ("netbsd", &["netbsd"]),
+    ("openbsd", &["openbsd"]),
+];
+
+const ARCH_ALIASES: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "x86-64", "amd64", "x64", "64bit"]),
+    ("aarch64", &["aarch64", "arm64", "armv8"]),
+    ("x86", &["i686", "i586", "i386", "386", "x86", "32bit", "win32"]),
+    ("arm", &["armv7", "armv7l", "armhf", "armv6", "armel", "arm"]),
+    ("universal", &["universal", "all"]),
+];
+
+const LIBC_ALIASES: &[(&str, &[&str])] = &[
+    ("musl", &["musl", "static"]),
+    ("gnu", &["gnu", "glibc", "gnueabihf"]),
+    ("msvc", &["msvc"]),
+];
+
+/// Suffixes of files that accompany releases but aren't installable.
+const UNUSABLE_SUFFIXES: &[(&str, &[&str])] = &[
+    (
+        "checksum",
+        &[
+            ".sha256",
+            ".sha512",
+            ".sha256sum",
+            ".sha512sum",
+            ".sha1",
+            ".md5",
+            ".sum",
+            ".txt",
+        ],
+    ),
+    (
+        "signature",
+        &[".sig", ".asc", ".minisig", ".pem", ".crt", ".cert", ".sigstore"],
+    ),
+    ("metadata", &[".json", ".sbom", ".spdx", ".intoto.jsonl", ".md"]),
+    (
+        "system package",
+        &[".deb", ".rpm", ".apk", ".pkg", ".dmg", ".msi", ".snap", ".flatpak"],
+    ),
+];
+
+/// Splits an asset name into its alphanumeric words.
+fn words(name: &str) -> Vec<&str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Finds the first canonical name whose aliases appear in `name`.
+///
+/// Aliases containing separators (like `x86_64`) are matched as substrings,
+/// everything else has to be a whole word so that `win` doesn't match
+/// `darwin`.
+fn find_alias(name: &str, words: &[&str], aliases: &[(&'static str, &[&str])]) -> Option<&'static str> {
+    aliases
+        .iter()
+        .find(|(_, aliases)| {
+            aliases.iter().any(|alias| {
+                if alias.contains(|c: char| !c.is_ascii_alphanumeric()) {
+                    name.contains(alias)
+                } else {
+                    words.contains(alias)
+                }
+            })
+        })
+        .map(|(canonical, _)| *canonical)
+}
+
+/// Pulls the OS, architecture, libc and format hints out of an asset name.
+pub fn tokenize(name: &str) -> AssetTokens {
+    let name = name.to_lowercase();
+    let words = words(&name);
+
+    let unusable = UNUSABLE_SUFFIXES
+        .iter()
+        .find(|(_, suffixes)| suffixes.iter().any(|suffix| name.ends_with(suffix)))
+        .map(|(kind, _)| *kind)
+        .or_else(|| {
+            if words
+                .iter()
+                .any(|word| matches!(*word, "checksums" | "sha256sums" | "sha512sums"))
+            {
+                Some("checksum")
+            } else if words
+                .iter()
+                .any(|word| matches!(*word, "src" | "source" | "vendor" | "vendored"))
+            {
+                Some("source")
+            } else {
+                None
+            }
+        });
+
+    let mut os = find_alias(&name, &words, OS_ALIASES);
+    if os.is_none() && name.ends_with(".exe") {
+        os = Some("windows");
+    }
+
+    AssetTokens {
+        os,
+        arch: find_alias(&name, &words, ARCH_ALIASES),
+        libc: find_alias(&name, &words, LIBC_ALIASES),
+        archive_format: ArchiveFormat::from_file_name(&name),
+        unusable,
+    }
+}
+
+/// A platform that release assets can be picked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    /// The LLVM target triple, such as `x86_64-unknown-linux-gnu`.
+    pub triple: String,
+
+    /// The operating system, using the same names as [`env::consts::OS`].
+    pub os: &'static str,
+
+    /// The architecture, using the same names as [`env::consts::ARCH`].
+    pub arch: &'static str,
+
+    /// The C library or ABI from the end of the triple, if any.
+    pub libc: Option<&'static str>,
+}
+
+impl Platform {
+    /// The platform Snowdrop is running on.
+    pub fn host() -> Self {
+        Self {
+            triple: current_platform::CURRENT_PLATFORM.to_string(),
+            os: env::consts::OS,
+            arch: env::consts::ARCH,
+            libc: triple_libc(current_platform::CURRENT_PLATFORM),
+        }
+    }
+
+    /// Works out the platform from an LLVM target triple, returning `None` if
+    /// its OS or architecture isn't recognized.
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        let components = triple.split('-').collect::<Vec<_>>();
+        let arch = match *components.first()? {
+            "x86_64" => "x86_64",
+            "aarch64" | "arm64" => "aarch64",
+            "i386" | "i586" | "i686" => "x86",
+            arch if arch.starts_with("arm") || arch.starts_with("thumbv7") => "arm",
+            _ => return None,
+        };
+        let os = components.iter().skip(1).find_map(|component| match *component {
+            "linux" => Some("linux"),
+            "darwin" => Some("macos"),
+            "windows" => Some("windows"),
+            "freebsd" => Some("freebsd"),
+            "netbsd" => Some("netbsd"),
+            "openbsd" => Some("openbsd"),
+            _ => None,
+        })?;
+
+        Some(Self {
+            triple: triple.to_string(),
+            os,
+            arch,
+            libc: triple_libc(triple),
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.triple == current_platform::CURRENT_PLATFORM
+    }
+}
+
+fn triple_libc(triple: &str) -> Option<&'static str> {
+    let environment = triple.rsplit('-').next()?;
+    ["musl", "gnu", "msvc"]
+        .into_iter()
+        .find(|libc| environment.starts_with(libc))
+}
+
+/// Scores how well an asset with the given tokens fits `platform`.
+///
+/// Returns `None` if the asset can't be used on the platform at all.
+pub fn score(tokens: &AssetTokens, platform: &Platform) -> Option<i32> {
+    if tokens.unusable.is_some() {
+        return None;
+    }
+
+    let mut score = 0;
+    match tokens.os {
+        Some(os) if os == platform.os => score += 100,
+        Some(_) => return None,
+        None => {}
+    }
+    match tokens.arch {
+        Some(arch) if arch == platform.arch => score += 50,
+        Some("universal") if platform.os == "macos" => score += 40,
+        // 32-bit x86 binaries still run on x86_64 hosts, but only as a last
+        // resort.
+        Some("x86") if platform.arch == "x86_64" => score += 5,
+        Some(_) => return None,
+        None => {}
+    }
+    match tokens.libc {
+        // Statically linked musl binaries work everywhere on Linux.
+        Some("musl") if platform.os == "linux" => score += 10,
+        Some("gnu") if platform.libc == Some("gnu") => score += 5,
+        Some("msvc") if platform.os == "windows" => score += 5,
+        Some(_) => score -= 5,
+        None => {}
+    }
+    score += match tokens.archive_format {
+        Some(ArchiveFormat::Zip) if platform.os == "windows" => 3,
+        Some(_) => 2,
+        None => 0,
+    };
+
+    Some(score)
+}
+
+/// Ranks the usable assets from best to worst fit for `platform`, dropping
+/// any that can't be used.
+pub fn rank(assets: Vec<Asset>, platform: &Platform) -> Vec<(Asset, AssetTokens, i32)> {
+    let mut ranked = assets
+        .into_iter()
+        .filter_map(|asset| {
+            let tokens = tokenize(&asset.name);
+            let score = score(&tokens, platform)?;
+            Some((asset, tokens, score))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|(_, _, score)| std::cmp::Reverse(*score));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn platform(triple: &str) -> Platform {
+        Platform::from_triple(triple).unwrap()
+    }
+
+    /// Picks the best scoring name for `triple`, like [`rank`] does for
+    /// assets.
+    fn best<'a>(names: &[&'a str], triple: &str) -> Option<&'a str> {
+        let platform = platform(triple);
+        names
+            .iter()
+            .filter_map(|name| Some((*name, score(&tokenize(name), &platform)?)))
+            .max_by_key(|(_, score)| *score)
+            .map(|(name, _)| name)
+    }
+
+    #[test]
+    fn tokenize_finds_platform_hints() {
+        let tokens = tokenize("ripgrep-13.0.0-x86_64-unknown-linux-musl.tar.gz");
+        assert_eq!(tokens.os, Some("linux"));
+        assert_eq!(tokens.arch, Some("x86_64"));
+        assert_eq!(tokens.libc, Some("musl"));
+        assert_eq!(tokens.archive_format, Some(ArchiveFormat::TarGz));
+        assert_eq!(tokens.unusable, None);
+
+        let tokens = tokenize("bat-v0.22.1-aarch64-apple-darwin.tar.gz");
+        assert_eq!((tokens.os, tokens.arch), (Some("macos"), Some("aarch64")));
+
+        let tokens = tokenize("tool_Windows_x64.zip");
+        assert_eq!((tokens.os, tokens.arch), (Some("windows"), Some("x86_64")));
+    }
+
+    #[test]
+    fn x86_64_is_not_mistaken_for_x86() {
+        assert_eq!(tokenize("tool-linux-x86_64").arch, Some("x86_64"));
+        assert_eq!(tokenize("tool-linux-x86-64").arch, Some("x86_64"));
+        assert_eq!(tokenize("tool-linux-x86").arch, Some("x86"));
+        assert_eq!(tokenize("tool-linux-i686").arch, Some("x86"));
+    }
+
+    #[test]
+    fn win_is_only_matched_as_a_whole_word() {
+        assert_eq!(tokenize("tool-darwin-amd64").os, Some("macos"));
+        assert_eq!(tokenize("tool-win-amd64.zip").os, Some("windows"));
+        assert_eq!(tokenize("winget-linux-amd64").os, Some("linux"));
+        assert_eq!(tokenize("tool.exe").os, Some("windows"));
+    }
+
+    #[test]
+    fn accompanying_files_are_unusable() {
+        assert_eq!(tokenize("tool-linux-amd64.tar.gz.sha256").unusable, Some("checksum"));
+        assert_eq!(tokenize("checksums.txt").unusable, Some("checksum"));
+        assert_eq!(tokenize("SHA256SUMS").unusable, Some("checksum"));
+        assert_eq!(tokenize("checksums.txt.sig").unusable, Some("signature"));
+        assert_eq!(tokenize("SHA256SUMS.asc").unusable, Some("signature"));
+        assert_eq!(tokenize("tool-1.0.0-src.tar.gz").unusable, Some("source"));
+        assert_eq!(tokenize("tool_1.0.0_amd64.deb").unusable, Some("system package"));
+        assert_eq!(
+            score(&tokenize("checksums.txt"), &platform("x86_64-unknown-linux-gnu")),
+            None
+        );
+    }
+
+    #[test]
+    fn other_platforms_are_unusable() {
+        let linux = platform("x86_64-unknown-linux-gnu");
+        assert_eq!(score(&tokenize("tool-darwin-amd64"), &linux), None);
+        assert_eq!(score(&tokenize("tool-linux-arm64"), &linux), None);
+        assert!(score(&tokenize("tool-linux-amd64"), &linux).is_some());
+    }
+
+    #[test]
+    fn best_fit_is_preferred() {
+        let names = [
+            "tool-x86_64-unknown-linux-gnu.tar.gz",
+            "tool-x86_64-unknown-linux-musl.tar.gz",
+            "tool-i686-unknown-linux-musl.tar.gz",
+            "tool-x86_64-apple-darwin.tar.gz",
+            "tool-universal-apple-darwin.tar.gz",
+            "tool-x86_64-pc-windows-msvc.zip",
+        ];
+        assert_eq!(
+            best(&names, "x86_64-unknown-linux-gnu"),
+            Some("tool-x86_64-unknown-linux-musl.tar.gz")
+        );
+        assert_eq!(
+            best(&names, "x86_64-apple-darwin"),
+            Some("tool-x86_64-apple-darwin.tar.gz")
+        );
+        assert_eq!(
+            best(&names, "aarch64-apple-darwin"),
+            Some("tool-universal-apple-darwin.tar.gz")
+        );
+        assert_eq!(
+            best(&names, "x86_64-pc-windows-msvc"),
+            Some("tool-x86_64-pc-windows-msvc.zip")
+        );
+        assert_eq!(best(&names, "aarch64-unknown-linux-gnu"), None);
+    }
+
+    #[test]
+    fn triples_are_parsed() {
+        let linux = platform("x86_64-unknown-linux-gnu");
+        assert_eq!((linux.os, linux.arch, linux.libc), ("linux", "x86_64", Some("gnu")));
+        let mac = platform("aarch64-apple-darwin");
+        assert_eq!((mac.os, mac.arch, mac.libc), ("macos", "aarch64", None));
+        let arm = platform("armv7-unknown-linux-gnueabihf");
+        assert_eq!((arm.arch, arm.libc), ("arm", Some("gnu")));
+        assert_eq!(Platform::from_triple("wasm32-unknown-unknown"), None);
+        assert_eq!(Platform::from_triple("x86_64-unknown-redox"), None);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{env, path::Path};
+
+use colored::Colorize;
+use miette::{IntoDiagnostic, Result};
+use tokio::fs;
+
+use super::layout::{FileKind, LayoutFile};
+use crate::dirs::{get_project_dirs, get_share_dir};
+
+/// Where the setup hints that have already been shown are recorded, one per
+/// line.
+const MARKER_FILE: &str = "shell_setup_shown";
+
+/// The shells setup is shown for, along with the file it goes in.
+const SHELLS: &[(&str, &str)] = &[
+    ("bash", "~/.bashrc"),
+    ("zsh", "~/.zshrc"),
+    ("fish", "~/.config/fish/config.fish"),
+];
+
+/// Prints the shell configuration needed for man pages and completions in
+/// Snowdrop's prefix to be found, the first time any of them are installed.
+pub async fn print_once(installed: &[LayoutFile]) -> Result<()> {
+    let marker = get_project_dirs()?.data_dir().join(MARKER_FILE);
+    let shown = fs::read_to_string(&marker).await.unwrap_or_default();
+    let is_new = |topic: &str| !shown.lines().any(|line| line == topic);
+
+    let man = is_new("man")
+        && installed
+            .iter()
+            .any(|file| matches!(file.kind, FileKind::ManPage { .. }));
+    let completions = is_new("completions")
+        && installed
+            .iter()
+            .any(|file| matches!(file.kind, FileKind::Completion(_)));
+    if !man && !completions {
+        return Ok(());
+    }
+
+    let share_dir = get_share_dir()?;
+    let what = match (man, completions) {
+        (true, true) => "man pages and shell completions",
+        (true, false) => "man pages",
+        _ => "shell completions",
+    };
+    println!(
+        "{}",
+        format!("Snowdrop installed {what}. To make them available, add this to your shell's configuration:").bold()
+    );
+
+    // Only show the user's own shell if it's one we know about.
+    let current_shell = env::var("SHELL").ok().and_then(|shell| {
+        let name = Path::new(&shell).file_name()?.to_string_lossy().to_string();
+        SHELLS.iter().any(|(known, _)| *known == name).then_some(name)
+    });
+    for (shell, config_file) in SHELLS {
+        if current_shell.as_deref().is_some_and(|current| current != *shell) {
+            continue;
+        }
+        println!("  {}", format!("# {config_file}").dimmed());
+        for line in setup_lines(shell, &share_dir, man, completions) {
+            println!("  {line}");
+        }
+    }
+
+    let mut shown = shown;
+    for (topic, new) in [("man", man), ("completions", completions)] {
+        if new {
+            shown.push_str(topic);
+            shown.push('\n');
+        }
+    }
+    fs::write(&marker, shown).await.into_diagnostic()
+}
+
+fn setup_lines(shell: &str, share_dir: &Path, man: bool, completions: bool) -> Vec<String> {
+    let share_dir = share_dir.display();
+    let mut lines = Vec::new();
+    match shell {
+        "fish" => {
+            if man {
+                lines.push(format!("set -gx MANPATH \"{share_dir}/man:$MANPATH\""));
+            }
+            if completions {
+                lines.push(format!(
+                    "set -p fish_complete_path \"{share_dir}/fish/vendor_completions.d\""
+                ));
+            }
+        }
+        shell => {
+            // If MANPATH was empty this leaves a trailing `:`, which keeps the
+            // system's default search path.
+            if man {
+                lines.push(format!("export MANPATH=\"{share_dir}/man:$MANPATH\""));
+            }
+            if completions && shell == "zsh" {
+                lines.push(format!(
+                    "fpath=(\"{share_dir}/zsh/site-functions\" $fpath)  # before compinit runs"
+                ));
+            } else if completions {
+                lines.push(format!(
+                    "export XDG_DATA_DIRS=\"{share_dir}:${{XDG_DATA_DIRS:-/usr/local/share:/usr/share}}\""
+                ));
+            }
+        }
+    }
+    lines
+}
@@ -0,0 +1,349 @@
+use std::path::Path;
+
+use globset::Glob;
+use log::info;
+use miette::{Diagnostic, IntoDiagnostic, Result};
+use octocrab::models::repos::Asset;
+use thiserror::Error;
+use tokio::fs;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum SignatureError {
+    #[error("The package's public key couldn't be parsed")]
+    #[diagnostic(help("This is a problem with the package index, try contacting the index maintainers"))]
+    InvalidPublicKey,
+
+    #[error("No signature was published for `{0}`")]
+    #[diagnostic(help("The package requires releases to be signed, so unsigned assets won't be installed"))]
+    MissingSignature(String),
+
+    #[error("The signature asset `{0}` doesn't sign the asset or its checksum file")]
+    UnknownSignedFile(String),
+
+    #[error("The signature of `{0}` is invalid")]
+    #[diagnostic(help(
+        "The download may have been tampered with, or signed with a different key, so it won't be installed"
+    ))]
+    InvalidSignature(String),
+}
+
+/// The key a package's releases are signed with.
+pub enum PublicKey {
+    Minisign(minisign_verify::PublicKey),
+    Ssh(Box<ssh_key::PublicKey>),
+}
+
+/// The namespace that `ssh-keygen -Y sign -n file` signs files under.
+const SSH_NAMESPACE: &str = "file";
+
+impl PublicKey {
+    /// Parses a minisign public key (either bare or as the full `.pub` file),
+    /// an OpenSSH public key, or a line from an `allowed_signers` file.
+    pub fn parse(key: &str) -> Result<Self> {
+        let key = key.trim();
+        if let Ok(public_key) = minisign_verify::PublicKey::from_base64(key) {
+            return Ok(Self::Minisign(public_key));
+        }
+        if let Ok(public_key) = minisign_verify::PublicKey::decode(key) {
+            return Ok(Self::Minisign(public_key));
+        }
+
+        // `allowed_signers` lines start with principals and options, so skip
+        // ahead to the key type.
+        let words = key.split_whitespace().collect::<Vec<_>>();
+        let start = words
+            .iter()
+            .position(|word| word.starts_with("ssh-") || word.starts_with("ecdsa-") || word.starts_with("sk-"))
+            .ok_or(SignatureError::InvalidPublicKey)?;
+        ssh_key::PublicKey::from_openssh(&words[start..].join(" "))
+            .map(|public_key| Self::Ssh(Box::new(public_key)))
+            .map_err(|_| SignatureError::InvalidPublicKey.into())
+    }
+
+    /// The extension that signature files made with this kind of key
+    /// conventionally have.
+    fn signature_extension(&self) -> &'static str {
+        match self {
+            Self::Minisign(_) => "minisig",
+            Self::Ssh(_) => "sig",
+        }
+    }
+
+    /// Checks that `signature` is a valid signature of `data`.
+    fn verify(&self, data: &[u8], signature: &str) -> bool {
+        match self {
+            Self::Minisign(public_key) => minisign_verify::Signature::decode(signature)
+                .and_then(|signature| public_key.verify(data, &signature, false))
+                .is_ok(),
+            Self::Ssh(public_key) => ssh_key::SshSig::from_pem(signature)
+                .and_then(|signature| public_key.verify(SSH_NAMESPACE, data, &signature))
+                .is_ok(),
+        }
+    }
+}
+
+/// What a signature asset signs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignedFile {
+    Asset,
+    ChecksumFile,
+}
+
+/// Finds the release asset holding the signature of either the chosen asset
+/// or its checksum file, preferring a signature of the asset itself.
+pub fn find_signature_asset(
+    assets: &[Asset],
+    key: &PublicKey,
+    asset_name: &str,
+    checksum_name: Option<&str>,
+    pattern: Option<&str>,
+) -> Result<(Asset, SignedFile)> {
+    let signed_file = |signature_name: &str| {
+        let signed_name = signature_name.rsplit_once('.').map_or(signature_name, |(name, _)| name);
+        if signed_name == asset_name {
+            Some(SignedFile::Asset)
+        } else if Some(signed_name) == checksum_name {
+            Some(SignedFile::ChecksumFile)
+        } else {
+            None
+        }
+    };
+
+    if let Some(pattern) = pattern {
+        let glob = Glob::new(pattern).into_diagnostic()?.compile_matcher();
+        let matches = assets
+            .iter()
+            .filter(|asset| glob.is_match(&asset.name))
+            .collect::<Vec<_>>();
+        // A pattern may match the signatures of several assets, so prefer the
+        // one belonging to the chosen asset.
+        let signature = matches
+            .iter()
+            .find(|asset| signed_file(&asset.name) == Some(SignedFile::Asset))
+            .or_else(|| matches.first())
+            .ok_or_else(|| SignatureError::MissingSignature(asset_name.to_string()))?;
+        let signed =
+            signed_file(&signature.name).ok_or_else(|| SignatureError::UnknownSignedFile(signature.name.clone()))?;
+        return Ok(((*signature).clone(), signed));
+    }
+
+    let extension = key.signature_extension();
+    let candidates = [Some(asset_name), checksum_name];
+    for name in candidates.into_iter().flatten() {
+        let signature_name = format!("{name}.{extension}");
+        if let Some(signature) = assets.iter().find(|asset| asset.name == signature_name) {
+            return Ok((signature.clone(), signed_file(&signature.name).unwrap()));
+        }
+    }
+    Err(SignatureError::MissingSignature(asset_name.to_string()).into())
+}
+
+/// Checks the file at `path` against the signature in `signature_path`.
+pub async fn verify(key: &PublicKey, path: &Path, signature_path: &Path) -> Result<()> {
+    let data = fs::read(path).await.into_diagnostic()?;
+    let signature = fs::read_to_string(signature_path).await.into_diagnostic()?;
+    let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+
+    if !key.verify(&data, &signature) {
+        return Err(SignatureError::InvalidSignature(file_name).into());
+    }
+    info!("Verified the signature of {file_name}.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const DATA: &str = "snowdrop test asset\n";
+
+    const MINISIGN_KEY: &str = "RWRLmqp3gdWALgusFT6jQNDasXzBCr2tkpHFkqrYrEcq9UqyLKf6/IF8";
+    const OTHER_MINISIGN_KEY: &str = "RWSPGLhBNiSHJRluBFY7jgTM07XoqaPqOW0Ww5BLMo8OvYGtO5t2PnpE";
+    const MINISIGN_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RURLmqp3gdWALguwAlMT+nOkswQVPH/c2Qsy7RI92aJcDgHSFsE1D6AsrSIrM6ytpnNg5Wveion7+Y51ULrix3VgP4+Jbj8gBg4=
+trusted comment: timestamp:1700000000\tfile:tool.tar.gz\thashed
+djQpstBS98ptJ3vomUQHKZYvhz3cZ/dVS7V+RFzi9089lq8lKuT/efcKPS2l3rJE1t3zLIRMGKsnXBgOHE1bAg==
+";
+
+    const SSH_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGyzrg7Da0C6yVcOTYcYr8kPqepy+zH6vCZCwLTGy3Jg test";
+    const OTHER_SSH_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOwIZpZ1qDbx49W+ZttMUOsuSWe95ErMEhlsS4HGgQEp other";
+    const SSH_SIGNATURE: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgbLOuDsNrQLrJVw5NhxivyQ+p6n
+L7Mfq8JkLAtMbLcmAAAAAEZmlsZQAAAAAAAAAGc2hhNTEyAAAAUwAAAAtzc2gtZWQyNTUx
+OQAAAEBWeC6KYB3vbK37e1xd25tZ/0K/tI9FkEe35qJbGz6NJTVI7bMJDPUw0j/t97PrcT
+Bo4eu8o5eHkyxGPSphJTQC
+-----END SSH SIGNATURE-----
+";
+
+    fn asset(name: &str) -> Asset {
+        let url = "https://api.github.com/x";
+        serde_json::from_value(json!({
+            "url": url, "browser_download_url": url, "id": 1, "node_id": "", "name": name,
+            "label": null, "state": "uploaded", "content_type": "application/octet-stream",
+            "size": 0, "download_count": 0, "created_at": "2023-01-01T00:00:00Z",
+            "updated_at": "2023-01-01T00:00:00Z",
+            "uploader": {
+                "login": "octocat", "id": 1, "node_id": "", "avatar_url": url, "gravatar_id": "",
+                "url": url, "html_url": url, "followers_url": url, "following_url": url,
+                "gists_url": url, "starred_url": url, "subscriptions_url": url,
+                "organizations_url": url, "repos_url": url, "events_url": url,
+                "received_events_url": url, "type": "User", "site_admin": false,
+            },
+        }))
+        .unwrap()
+    }
+
+    fn assets(names: &[&str]) -> Vec<Asset> {
+        names.iter().map(|name| asset(name)).collect()
+    }
+
+    /// Checks `data` against `signature` with `key`, going through files like
+    /// an install does.
+    async fn check(key: &str, data: &str, signature: &str) -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, signature_path) = (dir.path().join("tool.tar.gz"), dir.path().join("tool.tar.gz.sig"));
+        fs::write(&path, data).await.unwrap();
+        fs::write(&signature_path, signature).await.unwrap();
+        verify(&PublicKey::parse(key)?, &path, &signature_path).await
+    }
+
+    fn is_invalid_signature(result: Result<()>) -> bool {
+        matches!(
+            result.unwrap_err().downcast_ref::<SignatureError>(),
+            Some(SignatureError::InvalidSignature(_))
+        )
+    }
+
+    #[tokio::test]
+    async fn valid_minisign_signatures_are_accepted() {
+        check(MINISIGN_KEY, DATA, MINISIGN_SIGNATURE).await.unwrap();
+        let pub_file = format!("untrusted comment: minisign public key\n{MINISIGN_KEY}\n");
+        check(&pub_file, DATA, MINISIGN_SIGNATURE).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn valid_ssh_signatures_are_accepted() {
+        check(SSH_KEY, DATA, SSH_SIGNATURE).await.unwrap();
+        let allowed_signers = format!("releases@example.com namespaces=\"file\" {SSH_KEY}");
+        check(&allowed_signers, DATA, SSH_SIGNATURE).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn tampered_files_are_rejected() {
+        let tampered = DATA.replace("test", "evil");
+        assert!(is_invalid_signature(
+            check(MINISIGN_KEY, &tampered, MINISIGN_SIGNATURE).await
+        ));
+        assert!(is_invalid_signature(check(SSH_KEY, &tampered, SSH_SIGNATURE).await));
+    }
+
+    #[tokio::test]
+    async fn signatures_from_other_keys_are_rejected() {
+        assert!(is_invalid_signature(
+            check(OTHER_MINISIGN_KEY, DATA, MINISIGN_SIGNATURE).await
+        ));
+        assert!(is_invalid_signature(check(OTHER_SSH_KEY, DATA, SSH_SIGNATURE).await));
+        // A signature made with the other kind of key doesn't verify either.
+        assert!(is_invalid_signature(check(MINISIGN_KEY, DATA, SSH_SIGNATURE).await));
+        assert!(is_invalid_signature(check(SSH_KEY, DATA, MINISIGN_SIGNATURE).await));
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        for key in ["", "not a key", "ssh-ed25519 AAAA"] {
+            assert!(PublicKey::parse(key).is_err(), "{key}");
+        }
+    }
+
+    fn find(
+        names: &[&str],
+        key: &str,
+        checksum_name: Option<&str>,
+        pattern: Option<&str>,
+    ) -> Result<(String, SignedFile)> {
+        let key = PublicKey::parse(key).unwrap();
+        find_signature_asset(&assets(names), &key, "tool.tar.gz", checksum_name, pattern)
+            .map(|(asset, signed)| (asset.name, signed))
+    }
+
+    #[test]
+    fn missing_signatures_are_errors() {
+        for result in [
+            find(&["tool.tar.gz"], MINISIGN_KEY, None, None),
+            find(
+                &["tool.tar.gz", "checksums.txt"],
+                MINISIGN_KEY,
+                Some("checksums.txt"),
+                None,
+            ),
+            // A signature in the wrong format for the key doesn't count.
+            find(&["tool.tar.gz", "tool.tar.gz.sig"], MINISIGN_KEY, None, None),
+            find(
+                &["tool.tar.gz", "tool.tar.gz.minisig"],
+                MINISIGN_KEY,
+                None,
+                Some("*.asc"),
+            ),
+        ] {
+            assert!(matches!(
+                result.unwrap_err().downcast_ref::<SignatureError>(),
+                Some(SignatureError::MissingSignature(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn signed_files_are_told_apart() {
+        let checksums = Some("checksums.txt");
+        assert_eq!(
+            find(
+                &["tool.tar.gz", "checksums.txt", "checksums.txt.minisig"],
+                MINISIGN_KEY,
+                checksums,
+                None
+            )
+            .unwrap(),
+            ("checksums.txt.minisig".to_string(), SignedFile::ChecksumFile)
+        );
+        assert_eq!(
+            find(
+                &[
+                    "tool.tar.gz",
+                    "tool.tar.gz.minisig",
+                    "checksums.txt",
+                    "checksums.txt.minisig"
+                ],
+                MINISIGN_KEY,
+                checksums,
+                None
+            )
+            .unwrap(),
+            ("tool.tar.gz.minisig".to_string(), SignedFile::Asset)
+        );
+        assert_eq!(
+            find(&["tool.tar.gz", "tool.tar.gz.sig"], SSH_KEY, None, None).unwrap(),
+            ("tool.tar.gz.sig".to_string(), SignedFile::Asset)
+        );
+
+        // Patterns prefer the chosen asset's signature, and have to match
+        // something that signs it or its checksum file.
+        let names = ["other.tar.gz.asc", "tool.tar.gz.asc", "checksums.txt.asc"];
+        assert_eq!(
+            find(&names, MINISIGN_KEY, checksums, Some("*.asc")).unwrap(),
+            ("tool.tar.gz.asc".to_string(), SignedFile::Asset)
+        );
+        assert_eq!(
+            find(&names, MINISIGN_KEY, checksums, Some("checksums*")).unwrap(),
+            ("checksums.txt.asc".to_string(), SignedFile::ChecksumFile)
+        );
+        assert!(matches!(
+            find(&names, MINISIGN_KEY, checksums, Some("other*"))
+                .unwrap_err()
+                .downcast_ref::<SignatureError>(),
+            Some(SignatureError::UnknownSignedFile(_))
+        ));
+    }
+}
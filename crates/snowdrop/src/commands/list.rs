@@ -0,0 +1,37 @@
+use colored::Colorize;
+use miette::Result;
+
+use crate::receipts::Receipts;
+
+pub struct List;
+
+impl List {
+    pub async fn execute() -> Result<()> {
+        let receipts = Receipts::open()?.list().await?;
+
+        if receipts.is_empty() {
+            println!("{}", "No packages installed.".bold());
+            return Ok(());
+        }
+
+        println!("{}", format!("{} packages installed:", receipts.len()).bold());
+        for receipt in receipts {
+            if receipt.previous.is_empty() {
+                println!(
+                    "{} {}",
+                    format!(" - {}", receipt.name).blue().bold(),
+                    receipt.tag.dimmed()
+                );
+            } else {
+                println!(
+                    "{} {} {}",
+                    format!(" - {}", receipt.name).blue().bold(),
+                    receipt.tag.dimmed(),
+                    format!("(also kept: {})", receipt.previous.join(", ")).dimmed()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use colored::Colorize;
+use index_client::{metadata::PackageMetadata, resolver::VersionSpec};
+use log::info;
+use miette::{IntoDiagnostic, Result};
+use octocrab::models::repos::Release;
+use tokio::fs;
+
+use super::install::{Install, InstallPlan, Platform};
+use crate::{
+    config::get_config,
+    dirs::get_project_dirs,
+    project::{LockedAsset, LockedPackage, Lockfile, Project, LOCKFILE},
+};
+
+pub struct Lock;
+
+impl Lock {
+    /// Pins every package in the project's manifest to an exact release and
+    /// asset for each of its platforms.
+    ///
+    /// Packages that are already locked keep their pins as long as their
+    /// requirement hasn't changed, unless `upgrade` is set. Platforms added
+    /// since then are pinned to the same release.
+    pub async fn execute(upgrade: &bool) -> Result<()> {
+        let project = Project::find().await?;
+        let existing = project.read_lockfile().await?.unwrap_or_default();
+        let platforms = project.manifest.platforms()?;
+        let indexes = get_config()?.indexes().await?;
+
+        let cache_dir = get_project_dirs()?.cache_dir();
+        fs::create_dir_all(cache_dir).await.into_diagnostic()?;
+        let staging = tempfile::Builder::new()
+            .prefix("lock-")
+            .tempdir_in(cache_dir)
+            .into_diagnostic()?;
+
+        let mut lockfile = Lockfile::default();
+        for (name, requirement) in &project.manifest.packages {
+            let previous = existing
+                .get(name)
+                .filter(|previous| !*upgrade && &previous.requirement == requirement);
+            let pinned = |platform: &Platform| previous.and_then(|previous| previous.asset_for(&platform.triple));
+            let missing = platforms.iter().filter(|platform| pinned(platform).is_none()).count();
+            if let Some(previous) = previous.filter(|_| missing == 0) {
+                lockfile.packages.push(LockedPackage {
+                    assets: platforms
+                        .iter()
+                        .filter_map(|platform| pinned(platform).cloned())
+                        .collect(),
+                    ..previous.clone()
+                });
+                continue;
+            }
+
+            let package_metadata = indexes.get_package(name).await?;
+            // Platforms that are already pinned keep their assets, so adding a
+            // platform never moves the others to another release.
+            let release = match previous {
+                Some(previous) => {
+                    info!(
+                        "Locking {} {} for the platforms it isn't pinned for yet.",
+                        name.bold(),
+                        previous.tag
+                    );
+                    package_metadata.get_release(&previous.tag).await?
+                }
+                None => {
+                    info!("Locking {} ({requirement}).", name.bold());
+                    resolve(&package_metadata, requirement).await?
+                }
+            };
+
+            let mut assets = Vec::new();
+            for platform in &platforms {
+                if let Some(asset) = pinned(platform) {
+                    assets.push(asset.clone());
+                    continue;
+                }
+                let plan = InstallPlan::for_platform(&package_metadata, release.clone(), platform.clone()).await?;
+                let (_, sha256) = Install::fetch(&plan, staging.path()).await?;
+                assets.push(LockedAsset {
+                    platform: platform.triple.clone(),
+                    name: plan.asset.name,
+                    id: plan.asset.id.0,
+                    sha256,
+                });
+            }
+
+            lockfile.packages.push(LockedPackage {
+                name: name.clone(),
+                requirement: requirement.clone(),
+                repo: package_metadata.repo.clone(),
+                tag: release.tag_name,
+                assets,
+            });
+        }
+
+        project.write_lockfile(&lockfile).await?;
+        print_changes(&existing, &lockfile);
+        Ok(())
+    }
+}
+
+/// Finds the release of a package that a requirement from the manifest
+/// resolves to.
+async fn resolve(package_metadata: &PackageMetadata, requirement: &str) -> Result<Release> {
+    let release = match requirement {
+        "latest" => package_metadata.get_latest_release().await?,
+        requirement => {
+            package_metadata
+                .get_release_matching(&VersionSpec::parse(requirement), false)
+                .await?
+        }
+    };
+    Ok(release)
+}
+
+fn print_changes(existing: &Lockfile, lockfile: &Lockfile) {
+    let mut changes = Vec::new();
+    for package in &lockfile.packages {
+        match existing.get(&package.name) {
+            None => changes.push(format!(
+                "  {} {} {}",
+                "+".green().bold(),
+                package.name.bold(),
+                package.tag
+            )),
+            Some(previous) if previous.tag != package.tag => changes.push(format!(
+                "  {} {} {} -> {}",
+                "~".yellow().bold(),
+                package.name.bold(),
+                previous.tag.dimmed(),
+                package.tag
+            )),
+            Some(previous) if previous.assets != package.assets => changes.push(format!(
+                "  {} {} {} {}",
+                "~".yellow().bold(),
+                package.name.bold(),
+                package.tag,
+                "(assets changed)".dimmed()
+            )),
+            Some(_) => {}
+        }
+    }
+    for previous in &existing.packages {
+        if lockfile.get(&previous.name).is_none() {
+            changes.push(format!("  {} {}", "-".red().bold(), previous.name.bold()));
+        }
+    }
+
+    if changes.is_empty() {
+        println!("{}", format!("{LOCKFILE} is up to date.").bold());
+        return;
+    }
+    println!("{}", format!("Updated {LOCKFILE}:").bold());
+    for change in changes {
+        println!("{change}");
+    }
+}
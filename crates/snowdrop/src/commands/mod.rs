@@ -1,3 +1,13 @@
 pub mod auth;
+pub mod cache;
+pub mod info;
 pub mod install;
+pub mod list;
+pub mod lock;
+pub mod outdated;
+pub mod rollback;
 pub mod search;
+pub mod switch;
+pub mod sync;
+pub mod uninstall;
+pub mod upgrade;
@@ -0,0 +1,51 @@
+use colored::Colorize;
+use index_client::resolver::is_newer;
+use miette::Result;
+
+use crate::{config::get_config, receipts::Receipts};
+
+pub struct Outdated;
+
+impl Outdated {
+    pub async fn execute() -> Result<()> {
+        let config = get_config()?;
+        let indexes = config.indexes().await?;
+        let receipts = Receipts::open()?.list().await?;
+
+        let mut outdated = Vec::new();
+        for receipt in receipts {
+            let package_metadata = indexes.get_package(&receipt.lookup_name(&indexes)).await?;
+            let latest = package_metadata.get_latest_release().await?;
+            if is_newer(&latest.tag_name, &receipt.tag) {
+                outdated.push((receipt.name, receipt.tag, latest.tag_name));
+            }
+        }
+
+        if outdated.is_empty() {
+            println!("{}", "All packages are up to date.".green().bold());
+            return Ok(());
+        }
+
+        let name_width = outdated.iter().map(|(name, ..)| name.len()).max().unwrap_or(0).max(7);
+        let current_width = outdated
+            .iter()
+            .map(|(_, current, _)| current.len())
+            .max()
+            .unwrap_or(0)
+            .max(7);
+        println!(
+            "{}",
+            format!("{:name_width$}  {:current_width$}  {}", "Package", "Current", "Latest").bold()
+        );
+        for (name, current, latest) in outdated {
+            println!(
+                "{}  {}  {}",
+                format!("{name:name_width$}").blue().bold(),
+                format!("{current:current_width$}").red(),
+                latest.green()
+            );
+        }
+
+        Ok(())
+    }
+}
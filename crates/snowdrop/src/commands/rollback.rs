@@ -0,0 +1,29 @@
+use colored::Colorize;
+use miette::{miette, Result};
+
+use super::switch::switch_to;
+use crate::{receipts::Receipts, versions::InstalledVersions};
+
+pub struct Rollback;
+
+impl Rollback {
+    /// Switches back to the version of `package` that was active before the
+    /// current one.
+    pub async fn execute(package: &str) -> Result<()> {
+        let receipts = Receipts::open()?;
+        let active = receipts.installed(package).await?;
+
+        let target = match active.previous.last() {
+            Some(tag) => InstalledVersions::open(&active.name)?.get(tag).await?,
+            None => None,
+        };
+        let Some(target) = target else {
+            return Err(miette!(
+                "There's no earlier version of {} to roll back to",
+                package.bold()
+            ));
+        };
+
+        switch_to(&receipts, &active, target).await
+    }
+}
@@ -1,5 +1,6 @@
 use colored::Colorize;
-use index_client::IndexClient;
+use index_client::metadata::PackageMetadata;
+use log::debug;
 use miette::{Report, Result};
 use rust_fuzzy_search::fuzzy_search_best_n;
 
@@ -10,11 +11,9 @@ pub struct Search;
 impl Search {
     pub async fn execute(query: String, minimum_score: &f32, max_search_count: usize) -> Result<()> {
         let config = get_config()?;
-        let pat = config.get_pat()?;
-
-        let index_client = IndexClient::new(&config.index, env!("CARGO_PKG_VERSION"), pat.clone()).await?;
-        let names = index_client.get_names().await?;
-        let names_vec = names.iter().map(|name| name.as_str()).collect::<Vec<&str>>();
+        let indexes = config.indexes().await?;
+        let names = indexes.get_names().await?;
+        let names_vec = names.keys().map(|name| name.as_str()).collect::<Vec<&str>>();
         let raw_fuzzy_results = fuzzy_search_best_n(&query, names_vec.as_slice(), max_search_count);
         let matches: Vec<&(&str, f32)> = raw_fuzzy_results
             .iter()
@@ -27,10 +26,40 @@ impl Search {
 
         println!("{}", format!("{} matches found:", matches.len()).bold());
 
+        // With a single index there's nothing to tell apart.
+        let annotate = indexes.names().count() > 1;
         for (result, _) in matches {
-            println!("{}", format!(" - {result}").blue().bold())
+            let [index, shadowed @ ..] = names[*result].as_slice() else {
+                continue;
+            };
+            if !annotate {
+                println!("{}", format!(" - {result}").blue().bold());
+            } else {
+                let mut source = format!("({index}");
+                if !shadowed.is_empty() {
+                    source.push_str(&format!(", also in {}", shadowed.join(", ")));
+                }
+                source.push(')');
+                println!("{} {}", format!(" - {result}").blue().bold(), source.dimmed());
+            }
+
+            // `names.json` only has names, so the rest comes from each
+            // package's own metadata.
+            match indexes.get_package(&format!("{index}/{result}")).await {
+                Ok(package_metadata) => print_details(&package_metadata),
+                Err(err) => debug!("Couldn't get the details of {result}: {err}"),
+            }
         }
 
         Ok(())
     }
 }
+
+fn print_details(package_metadata: &PackageMetadata) {
+    if let Some(description) = &package_metadata.description {
+        println!("   {description}");
+    }
+    if !package_metadata.tags.is_empty() {
+        println!("   {}", format!("tags: {}", package_metadata.tags.join(", ")).dimmed());
+    }
+}
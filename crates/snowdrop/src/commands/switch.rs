@@ -0,0 +1,52 @@
+use colored::Colorize;
+use miette::{miette, Result};
+
+use crate::{
+    config::get_config,
+    receipts::{Receipt, Receipts},
+    versions::{self, InstalledVersions},
+};
+
+pub struct Switch;
+
+impl Switch {
+    pub async fn execute(package: &str, version: &str) -> Result<()> {
+        let receipts = Receipts::open()?;
+        let active = receipts.installed(package).await?;
+
+        let installed = InstalledVersions::open(&active.name)?.list().await?;
+        let tags = installed.iter().map(|receipt| receipt.tag.clone()).collect::<Vec<_>>();
+        let Some(target) = installed.into_iter().find(|receipt| same_tag(&receipt.tag, version)) else {
+            return Err(miette!(
+                "Version {} of {} isn't installed (installed versions: {})",
+                version.bold(),
+                package.bold(),
+                tags.join(", ")
+            ));
+        };
+        if target.tag == active.tag {
+            println!("{}", format!("{package} {} is already active.", active.tag).bold());
+            return Ok(());
+        }
+
+        switch_to(&receipts, &active, target).await
+    }
+}
+
+/// Makes the installed version described by `target` the active one, and
+/// reports the switch from `active`.
+pub async fn switch_to(receipts: &Receipts, active: &Receipt, target: Receipt) -> Result<()> {
+    let receipt = versions::activate(receipts, target, get_config()?.keep_versions).await?;
+    println!(
+        "{} {} -> {}",
+        format!("Switched {}:", receipt.name).bold(),
+        active.tag.red(),
+        receipt.tag.green()
+    );
+    Ok(())
+}
+
+/// Whether two tags name the same version, ignoring any `v` prefix.
+fn same_tag(a: &str, b: &str) -> bool {
+    a == b || a.trim_start_matches('v') == b.trim_start_matches('v')
+}
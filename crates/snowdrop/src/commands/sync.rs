@@ -0,0 +1,142 @@
+use colored::Colorize;
+use index_client::resolver::parse_tag_version;
+use miette::Result;
+
+use crate::{
+    commands::{
+        install::{Install, Platform},
+        uninstall::uninstall,
+    },
+    config::get_config,
+    project::{LockError, LockedPackage, Project, LOCKFILE},
+    receipts::{Receipt, Receipts},
+};
+
+/// A change `sync` needs to make to the installed packages.
+enum Change<'a> {
+    /// Install the pinned release, replacing the installed one if there is
+    /// one.
+    Install {
+        locked: &'a LockedPackage,
+        installed: Option<Receipt>,
+    },
+
+    /// Uninstall a package that isn't in the lockfile.
+    Remove(Receipt),
+}
+
+pub struct Sync;
+
+impl Sync {
+    /// Installs, upgrades and downgrades packages until they match the
+    /// project's `snowdrop.lock`, uninstalling any other packages if `prune`
+    /// is set.
+    pub async fn execute(prune: &bool, dry_run: &bool, yes: &bool) -> Result<()> {
+        let project = Project::find().await?;
+        let lockfile = project.read_lockfile().await?.ok_or(LockError::NoLockfile)?;
+        lockfile.check_up_to_date(&project.manifest)?;
+
+        let platform = Platform::host();
+        let receipts = Receipts::open()?;
+        let mut changes = Vec::new();
+        for locked in &lockfile.packages {
+            let asset = locked
+                .asset_for(&platform.triple)
+                .ok_or_else(|| LockError::PlatformNotLocked {
+                    package: locked.name.clone(),
+                    platform: platform.triple.clone(),
+                })?;
+            match receipts.get(locked.package_name()).await? {
+                Some(receipt) if receipt.asset_id == asset.id && receipt.asset_sha256 == asset.sha256 => {}
+                installed => changes.push(Change::Install { locked, installed }),
+            }
+        }
+        if *prune {
+            for receipt in receipts.list().await? {
+                if !lockfile
+                    .packages
+                    .iter()
+                    .any(|locked| locked.package_name() == receipt.name)
+                {
+                    changes.push(Change::Remove(receipt));
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            println!(
+                "{}",
+                format!("Installed packages are in sync with {LOCKFILE}.")
+                    .green()
+                    .bold()
+            );
+            return Ok(());
+        }
+
+        println!(
+            "{}",
+            format!("{} changes to sync with {LOCKFILE}:", changes.len()).bold()
+        );
+        for change in &changes {
+            print_change(change);
+        }
+        if *dry_run {
+            return Ok(());
+        }
+
+        Install::confirm("Apply these changes?", *yes)?;
+
+        let indexes = get_config()?.indexes().await?;
+        for change in changes {
+            match change {
+                Change::Install { locked, .. } => {
+                    let package_metadata = indexes.get_package(&locked.name).await?;
+                    let plan = Install::plan_locked(&package_metadata, locked).await?;
+                    Install::apply(&package_metadata, plan, &receipts).await?;
+                }
+                Change::Remove(receipt) => uninstall(&receipts, &receipt).await?,
+            }
+        }
+
+        println!(
+            "{}",
+            format!("Installed packages are in sync with {LOCKFILE}.")
+                .green()
+                .bold()
+        );
+        Ok(())
+    }
+}
+
+fn print_change(change: &Change) {
+    match change {
+        Change::Install {
+            locked,
+            installed: None,
+        } => println!("  {} {} {}", "+".green().bold(), locked.name.bold(), locked.tag),
+        Change::Install {
+            locked,
+            installed: Some(receipt),
+        } => {
+            let direction = match (parse_tag_version(&receipt.tag), parse_tag_version(&locked.tag)) {
+                (Some(old), Some(new)) if new < old => "downgrade",
+                _ if receipt.tag == locked.tag => "reinstall",
+                _ => "upgrade",
+            };
+            println!(
+                "  {} {} {} -> {} {}",
+                "~".yellow().bold(),
+                locked.name.bold(),
+                receipt.tag.dimmed(),
+                locked.tag,
+                format!("({direction})").dimmed()
+            );
+        }
+        Change::Remove(receipt) => println!(
+            "  {} {} {}",
+            "-".red().bold(),
+            receipt.name.bold(),
+            receipt.tag.dimmed()
+        ),
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use colored::Colorize;
+use log::{info, warn};
+use miette::{IntoDiagnostic, Result};
+use tokio::fs;
+
+use crate::{
+    digest::sha256_file,
+    dirs::get_project_dirs,
+    receipts::{Receipt, Receipts},
+    versions::InstalledVersions,
+};
+
+pub struct Uninstall;
+
+impl Uninstall {
+    pub async fn execute(package: &str, dry_run: &bool) -> Result<()> {
+        let receipts = Receipts::open()?;
+        let receipt = receipts.installed(package).await?;
+
+        if *dry_run {
+            remove_installed_files(&receipt, true).await?;
+            let versions = InstalledVersions::open(&receipt.name)?;
+            let versions_dir = versions.root();
+            if fs::try_exists(versions_dir).await.into_diagnostic()? {
+                println!("Would remove {}", versions_dir.display().to_string().bold());
+            }
+            return Ok(());
+        }
+        uninstall(&receipts, &receipt).await
+    }
+}
+
+/// Removes an installed package's files, every version of it that's kept,
+/// and its receipt.
+pub async fn uninstall(receipts: &Receipts, receipt: &Receipt) -> Result<()> {
+    remove_installed_files(receipt, false).await?;
+    InstalledVersions::open(&receipt.name)?.remove_all().await?;
+    receipts.remove(&receipt.name).await?;
+    info!("Uninstalled {} {}.", receipt.name.bold(), receipt.tag.bold());
+    Ok(())
+}
+
+/// Removes every file recorded in `receipt`, along with any directories
+/// that are left empty afterwards.
+///
+/// Files outside of Snowdrop's data directory, and files that have changed
+/// since they were installed, are never touched.
+pub async fn remove_installed_files(receipt: &Receipt, dry_run: bool) -> Result<()> {
+    let data_dir = get_project_dirs()?.data_dir();
+
+    for file in &receipt.files {
+        let path = &file.path;
+        if !path.starts_with(data_dir) {
+            warn!(
+                "Not removing {}, as it is outside of Snowdrop's data directory.",
+                path.display()
+            );
+            continue;
+        }
+        let exists = fs::try_exists(path).await.into_diagnostic()?;
+        let is_link = fs::symlink_metadata(path)
+            .await
+            .is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if !exists && !is_link {
+            continue;
+        }
+        // A link into a version that's already been removed can always go,
+        // but anything else has to be unchanged.
+        if exists && sha256_file(path).await? != file.sha256 {
+            warn!(
+                "Not removing {}, as it has been modified since it was installed.",
+                path.display()
+            );
+            continue;
+        }
+
+        if dry_run {
+            println!("Would remove {}", path.display().to_string().bold());
+            continue;
+        }
+        fs::remove_file(path).await.into_diagnostic()?;
+        info!("Removed {}.", path.display().to_string().bold());
+        remove_empty_parents(path, data_dir).await?;
+    }
+
+    Ok(())
+}
+
+/// Removes the empty ancestors of `path`, stopping at `root`.
+async fn remove_empty_parents(path: &Path, root: &Path) -> Result<()> {
+    let mut current = path.parent();
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+        if fs::read_dir(dir)
+            .await
+            .into_diagnostic()?
+            .next_entry()
+            .await
+            .into_diagnostic()?
+            .is_some()
+        {
+            break;
+        }
+        fs::remove_dir(dir).await.into_diagnostic()?;
+        current = dir.parent();
+    }
+    Ok(())
+}
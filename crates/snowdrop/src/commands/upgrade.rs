@@ -0,0 +1,75 @@
+use colored::Colorize;
+use index_client::resolver::is_newer;
+use log::info;
+use miette::{miette, Result};
+
+use crate::{commands::install::Install, config::get_config, receipts::Receipts};
+
+pub struct Upgrade;
+
+impl Upgrade {
+    pub async fn execute(packages: &[String], all: &bool, yes: &bool) -> Result<()> {
+        if packages.is_empty() && !all {
+            return Err(miette!("No packages specified, pass `--all` to upgrade everything"));
+        }
+
+        let config = get_config()?;
+        let indexes = config.indexes().await?;
+        let receipts = Receipts::open()?;
+
+        let installed = if *all {
+            receipts.list().await?
+        } else {
+            let mut installed = Vec::new();
+            for package in packages {
+                installed.push(receipts.installed(package).await?);
+            }
+            installed
+        };
+
+        let mut upgrades = Vec::new();
+        for receipt in installed {
+            info!("Checking {} for updates.", receipt.name.bold());
+            let package_metadata = indexes.get_package(&receipt.lookup_name(&indexes)).await?;
+            let release = package_metadata.get_latest_release().await?;
+            if is_newer(&release.tag_name, &receipt.tag) {
+                upgrades.push((receipt, package_metadata, release));
+            }
+        }
+
+        if upgrades.is_empty() {
+            println!("{}", "All packages are up to date.".green().bold());
+            return Ok(());
+        }
+
+        println!("{}", format!("{} packages can be upgraded:", upgrades.len()).bold());
+        for (receipt, _, release) in &upgrades {
+            println!(
+                "{} {} -> {}",
+                format!(" - {}", receipt.name).blue().bold(),
+                receipt.tag.red(),
+                release.tag_name.green()
+            );
+        }
+
+        Install::confirm("Upgrade these packages?", *yes)?;
+
+        let mut summary = Vec::new();
+        for (receipt, package_metadata, release) in upgrades {
+            let new_receipt = Install::install_release(&package_metadata, release, &receipts).await?;
+            summary.push((receipt.name, receipt.tag, new_receipt.tag));
+        }
+
+        println!("{}", format!("Upgraded {} packages:", summary.len()).bold());
+        for (name, old_tag, new_tag) in summary {
+            println!(
+                "{} {} -> {}",
+                format!(" - {name}").blue().bold(),
+                old_tag.red(),
+                new_tag.green()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
-use index_client::error::IndexClientError;
-use miette::{IntoDiagnostic, Result, WrapErr};
+use std::{collections::HashSet, time::Duration};
+
+use index_client::{error::IndexClientError, http_cache::HttpCache, indexes::Indexes};
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
 use secrecy::SecretString;
 use serde::Deserialize;
 
-use crate::{defaults::default_package_index, dirs::get_project_dirs};
+use crate::{
+    defaults::{default_index_cache_ttl, default_keep_versions, default_package_index, DEFAULT_INDEX_NAME},
+    dirs::get_project_dirs,
+};
 
 pub fn get_config() -> Result<Config> {
     let config = config::Config::builder()
@@ -28,12 +33,39 @@ pub fn get_config() -> Result<Config> {
 
 #[derive(Deserialize)]
 pub struct Config {
-    /// The root URL of the package index.
+    /// The root of the package index, either an HTTP(S) URL or a local
+    /// checkout of the index given as a path or `file://` URL.
     #[serde(default = "default_package_index")]
     pub index: String,
 
+    /// The package indexes to search, from highest to lowest priority. If
+    /// this is empty, only `index` is searched.
+    #[serde(default)]
+    pub indexes: Vec<IndexConfig>,
+
+    /// How many seconds responses from HTTP indexes are used for before
+    /// they're revalidated. Setting this to 0 revalidates every request.
+    #[serde(default = "default_index_cache_ttl")]
+    pub index_cache_ttl: u64,
+
     /// The GitHub PAT.
     pub pat: Option<SecretString>,
+
+    /// How many previously active versions of each package to keep
+    /// installed, so they can be switched back to.
+    #[serde(default = "default_keep_versions")]
+    pub keep_versions: usize,
+}
+
+/// A named package index.
+#[derive(Deserialize)]
+pub struct IndexConfig {
+    /// The name packages from the index can be qualified with, like
+    /// `internal/deploy-cli`.
+    pub name: String,
+
+    /// The root of the index, in any form `index` accepts.
+    pub url: String,
 }
 
 type PatError = IndexClientError;
@@ -46,4 +78,32 @@ impl Config {
         };
         Ok(pat)
     }
+
+    /// Connects to the configured package indexes.
+    pub async fn indexes(&self) -> Result<Indexes> {
+        let pat = self.get_pat()?;
+        let indexes = if self.indexes.is_empty() {
+            vec![(DEFAULT_INDEX_NAME.to_string(), self.index.clone())]
+        } else {
+            let mut seen = HashSet::new();
+            for index in &self.indexes {
+                if index.name.is_empty() || index.name.contains('/') {
+                    return Err(miette!("`{}` isn't a valid index name", index.name)).wrap_err("failed to read config");
+                }
+                if !seen.insert(&index.name) {
+                    return Err(miette!("The index `{}` is configured more than once", index.name))
+                        .wrap_err("failed to read config");
+                }
+            }
+            self.indexes
+                .iter()
+                .map(|index| (index.name.clone(), index.url.clone()))
+                .collect()
+        };
+        let cache = HttpCache::new(
+            get_project_dirs()?.cache_dir().join("index"),
+            Duration::from_secs(self.index_cache_ttl),
+        );
+        Ok(Indexes::new(&indexes, env!("CARGO_PKG_VERSION"), pat.clone(), Some(cache)).await?)
+    }
 }
@@ -1,10 +1,21 @@
 use colored::Colorize;
 use dialoguer::{console::style, theme::ColorfulTheme};
 
+/// The name of the index given by `index`, when no `indexes` are configured.
+pub const DEFAULT_INDEX_NAME: &str = "default";
+
 pub fn default_package_index() -> String {
     "https://raw.githubusercontent.com/snowdroppm/index/main".to_string()
 }
 
+pub const fn default_index_cache_ttl() -> u64 {
+    5 * 60
+}
+
+pub const fn default_keep_versions() -> usize {
+    2
+}
+
 pub fn theme() -> ColorfulTheme {
     ColorfulTheme {
         prompt_prefix: style(" ?".cyan().bold().to_string()),
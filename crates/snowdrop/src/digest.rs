@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use miette::{IntoDiagnostic, Result};
+use sha2::{Digest, Sha256, Sha512};
+use tokio::{fs::File, io::AsyncReadExt};
+
+/// Computes the hex-encoded SHA-256 digest of the file at `path`.
+pub async fn sha256_file(path: &Path) -> Result<String> {
+    digest_file::<Sha256>(path).await
+}
+
+/// Computes the hex-encoded SHA-512 digest of the file at `path`.
+pub async fn sha512_file(path: &Path) -> Result<String> {
+    digest_file::<Sha512>(path).await
+}
+
+async fn digest_file<D: Digest>(path: &Path) -> Result<String> {
+    let mut file = File::open(path).await.into_diagnostic()?;
+    let mut hasher = D::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await.into_diagnostic()?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use directories::ProjectDirs;
 use miette::{Report, Result};
 use once_cell::sync::OnceCell;
@@ -13,3 +15,14 @@ pub fn get_project_dirs() -> Result<&'static ProjectDirs> {
         }
     })
 }
+
+/// The directory that installed executables are placed in.
+pub fn get_bin_dir() -> Result<PathBuf> {
+    Ok(get_project_dirs()?.data_dir().join("bin"))
+}
+
+/// The directory that man pages, shell completions and other supporting
+/// files are placed in, laid out like `/usr/share`.
+pub fn get_share_dir() -> Result<PathBuf> {
+    Ok(get_project_dirs()?.data_dir().join("share"))
+}
@@ -6,22 +6,31 @@ use clap::Parser;
 use log::LevelFilter;
 use miette::{IntoDiagnostic, Result};
 
+mod cache;
 mod cli_struct;
 mod commands;
 mod config;
 mod defaults;
+mod digest;
 mod dirs;
+mod progress;
+mod project;
+mod receipts;
+mod units;
+mod versions;
 
 use cli_struct::Cli;
+use progress::ProgressLogger;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    styled_env_logger::formatted_builder()
+    let logger = styled_env_logger::formatted_builder()
         .filter(None, LevelFilter::Info)
         .parse_filters(&env::var("RUST_LOG").unwrap_or_else(|_| String::from("INFO")))
         .parse_write_style(&env::var("RUST_LOG_STYLE").unwrap_or_else(|_| String::from("auto")))
-        .try_init()
-        .into_diagnostic()?;
+        .build();
+    let max_level = logger.filter();
+    ProgressLogger::init(Box::new(logger), max_level).into_diagnostic()?;
 
     let command = Cli::parse().command;
     command.execute().await?;
@@ -0,0 +1,51 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use once_cell::sync::Lazy;
+
+static MULTI_PROGRESS: Lazy<MultiProgress> = Lazy::new(MultiProgress::new);
+
+/// A logger that hides any progress bars while it writes, so log lines don't
+/// get mangled by a bar that's being redrawn.
+pub struct ProgressLogger {
+    inner: Box<dyn Log>,
+}
+
+impl ProgressLogger {
+    /// Installs `inner`, wrapped in a [`ProgressLogger`], as the global
+    /// logger.
+    pub fn init(inner: Box<dyn Log>, max_level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(Self { inner }))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for ProgressLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.enabled(record.metadata()) {
+            MULTI_PROGRESS.suspend(|| self.inner.log(record));
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+/// Creates a progress bar for downloading `len` bytes of `name`.
+pub fn download_bar(name: &str, len: u64) -> ProgressBar {
+    let bar = MULTI_PROGRESS.add(ProgressBar::new(len));
+    bar.set_style(
+        ProgressStyle::with_template(
+            " {msg:.bold} [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}, ETA {eta})",
+        )
+        .unwrap()
+        .progress_chars("=> "),
+    );
+    bar.set_message(name.to_string());
+    bar
+}
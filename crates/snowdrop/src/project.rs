@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, env, path::PathBuf};
+
+use index_client::{files::write_atomically, indexes::split_qualified};
+use miette::{Diagnostic, IntoDiagnostic, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::fs;
+
+use crate::commands::install::Platform;
+
+/// The name of the file a project declares the packages it needs in.
+pub const MANIFEST_FILE: &str = "snowdrop.toml";
+
+/// The name of the file the packages a project needs are pinned in.
+pub const LOCKFILE: &str = "snowdrop.lock";
+
+/// The lockfile format written by this version of Snowdrop.
+const LOCKFILE_VERSION: u32 = 1;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum LockError {
+    #[error("Couldn't find a {MANIFEST_FILE} in {} or any of its parents", .0.display())]
+    #[diagnostic(help("Create a {MANIFEST_FILE} listing the packages this project needs"))]
+    NoManifest(PathBuf),
+
+    #[error("This project doesn't have a {LOCKFILE}")]
+    #[diagnostic(help("Run `snowdrop lock` to create one"))]
+    NoLockfile,
+
+    #[error("{LOCKFILE} was written by a newer version of Snowdrop (format version {0})")]
+    #[diagnostic(help("Upgrade Snowdrop to use this lockfile"))]
+    UnsupportedLockfile(u32),
+
+    #[error("`{0}` isn't a target triple Snowdrop recognizes")]
+    UnknownPlatform(String),
+
+    #[error("{0} isn't pinned in {LOCKFILE}")]
+    #[diagnostic(help("Add it to {MANIFEST_FILE} and run `snowdrop lock`"))]
+    PackageNotLocked(String),
+
+    #[error("{LOCKFILE} is out of date, as {0} isn't pinned with the requirement in {MANIFEST_FILE}")]
+    #[diagnostic(help("Run `snowdrop lock` to update it"))]
+    OutOfDate(String),
+
+    #[error("{LOCKFILE} doesn't pin an asset of {package} for {platform}")]
+    #[diagnostic(help("Add `{platform}` to the platforms in {MANIFEST_FILE} and run `snowdrop lock`"))]
+    PlatformNotLocked { package: String, platform: String },
+
+    #[error("The asset `{asset_name}` pinned by {LOCKFILE} is no longer part of release {tag}")]
+    #[diagnostic(help("Run `snowdrop lock --upgrade` to pin a new asset"))]
+    AssetGone { asset_name: String, tag: String },
+
+    #[error("`{asset_name}` doesn't have the digest pinned by {LOCKFILE}")]
+    #[diagnostic(help(
+        "Expected SHA-256 {expected}, but the download has {actual}. The release asset may have been replaced."
+    ))]
+    DigestMismatch {
+        asset_name: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// The packages a project needs, as declared in its `snowdrop.toml`.
+#[derive(Deserialize, Debug)]
+pub struct Manifest {
+    /// The target triples to pin assets for. Only the host is pinned if this
+    /// is empty.
+    #[serde(default)]
+    pub platforms: Vec<String>,
+
+    /// The packages the project needs, mapped to a release tag, a semver
+    /// requirement or `latest`.
+    #[serde(default)]
+    pub packages: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// The platforms assets should be pinned for.
+    pub fn platforms(&self) -> Result<Vec<Platform>> {
+        if self.platforms.is_empty() {
+            return Ok(vec![Platform::host()]);
+        }
+
+        self.platforms
+            .iter()
+            .map(|triple| {
+                Platform::from_triple(triple).ok_or_else(|| LockError::UnknownPlatform(triple.clone()).into())
+            })
+            .collect()
+    }
+}
+
+/// The exact releases and assets a project's packages resolved to, as
+/// recorded in its `snowdrop.lock`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Lockfile {
+    pub version: u32,
+
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedPackage {
+    /// The name of the package as the manifest gives it, which may be
+    /// qualified with an index, like `internal/deploy-cli`.
+    pub name: String,
+
+    /// The requirement from the manifest this was resolved from.
+    pub requirement: String,
+
+    /// The `[owner, repo]` pair the package is downloaded from.
+    pub repo: [String; 2],
+
+    /// The tag of the pinned release.
+    pub tag: String,
+
+    /// The pinned asset for each platform.
+    #[serde(default, rename = "asset")]
+    pub assets: Vec<LockedAsset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedAsset {
+    /// The target triple the asset was picked for.
+    pub platform: String,
+
+    /// The name of the release asset.
+    pub name: String,
+
+    /// The GitHub ID of the release asset.
+    pub id: u64,
+
+    /// The SHA-256 digest the release asset must have.
+    pub sha256: String,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Checks that every package in `manifest` is pinned with its current
+    /// requirement.
+    pub fn check_up_to_date(&self, manifest: &Manifest) -> Result<(), LockError> {
+        for (name, requirement) in &manifest.packages {
+            if self.get(name).is_none_or(|locked| &locked.requirement != requirement) {
+                return Err(LockError::OutOfDate(name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LockedPackage {
+    /// The name of the package without the index it may be qualified with,
+    /// which is what it's installed as.
+    pub fn package_name(&self) -> &str {
+        split_qualified(&self.name).1
+    }
+
+    /// Gets the asset pinned for the platform with the target triple
+    /// `platform`.
+    pub fn asset_for(&self, platform: &str) -> Option<&LockedAsset> {
+        self.assets.iter().find(|asset| asset.platform == platform)
+    }
+}
+
+/// A directory with a `snowdrop.toml` in it.
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    /// Finds the project the current directory is part of, by looking for a
+    /// `snowdrop.toml` in it and each of its parents.
+    pub async fn find() -> Result<Self> {
+        let current_dir = env::current_dir().into_diagnostic()?;
+        for dir in current_dir.ancestors() {
+            let path = dir.join(MANIFEST_FILE);
+            if fs::try_exists(&path).await.into_diagnostic()? {
+                let contents = fs::read_to_string(&path).await.into_diagnostic()?;
+                let manifest = toml::from_str(&contents)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to parse manifest at {}", path.display()))?;
+                return Ok(Self {
+                    root: dir.to_path_buf(),
+                    manifest,
+                });
+            }
+        }
+
+        Err(LockError::NoManifest(current_dir).into())
+    }
+
+    pub fn lockfile_path(&self) -> PathBuf {
+        self.root.join(LOCKFILE)
+    }
+
+    /// Reads the project's lockfile, if it has one.
+    pub async fn read_lockfile(&self) -> Result<Option<Lockfile>> {
+        let path = self.lockfile_path();
+        if !fs::try_exists(&path).await.into_diagnostic()? {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path).await.into_diagnostic()?;
+        let lockfile: Lockfile = toml::from_str(&contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse lockfile at {}", path.display()))?;
+        if lockfile.version > LOCKFILE_VERSION {
+            return Err(LockError::UnsupportedLockfile(lockfile.version).into());
+        }
+        Ok(Some(lockfile))
+    }
+
+    /// Writes `lockfile` as the project's lockfile, replacing the existing
+    /// one.
+    pub async fn write_lockfile(&self, lockfile: &Lockfile) -> Result<()> {
+        let contents = format!(
+            "# This file is generated by `snowdrop lock`, don't edit it by hand.\n\n{}",
+            toml::to_string(lockfile).into_diagnostic()?
+        );
+        write_atomically(&self.lockfile_path(), contents)
+            .await
+            .into_diagnostic()
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use index_client::{
+    files::write_atomically,
+    indexes::{split_qualified, Indexes},
+    metadata::check_name,
+};
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::dirs::get_project_dirs;
+
+/// A record of everything Snowdrop did when it installed a package.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Receipt {
+    /// The name of the package in the index.
+    pub name: String,
+
+    /// The name of the index the package was installed from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+
+    /// The `[owner, repo]` pair the package was downloaded from.
+    pub repo: [String; 2],
+
+    /// The tag of the installed release.
+    pub tag: String,
+
+    /// The name of the release asset that was downloaded.
+    pub asset_name: String,
+
+    /// The GitHub ID of the release asset that was downloaded.
+    pub asset_id: u64,
+
+    /// The URL the release asset was downloaded from.
+    pub download_url: String,
+
+    /// The SHA-256 digest of the downloaded release asset.
+    pub asset_sha256: String,
+
+    /// The tags of the versions that were active before this one and are
+    /// still installed, least recently active first.
+    #[serde(default)]
+    pub previous: Vec<String>,
+
+    /// Every file that was written during installation.
+    pub files: Vec<InstalledFile>,
+}
+
+impl Receipt {
+    /// Reads the receipt stored at `path`.
+    pub async fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).await.into_diagnostic()?;
+        toml::from_str(&contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse receipt at {}", path.display()))
+    }
+
+    /// Stores the receipt at `path`, replacing any existing file.
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string(self).into_diagnostic()?;
+        write_atomically(path, contents).await.into_diagnostic()
+    }
+
+    /// The name to look the package up by, qualified with the index it was
+    /// installed from if that index is still configured.
+    pub fn lookup_name(&self, indexes: &Indexes) -> String {
+        match &self.index {
+            Some(index) if indexes.contains(index) => format!("{index}/{}", self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// Whether the file at `path` was installed by this package.
+    pub fn owns(&self, path: &Path) -> bool {
+        self.files.iter().any(|file| file.path == path)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledFile {
+    /// The absolute path of the installed file.
+    pub path: PathBuf,
+
+    /// The SHA-256 digest of the file at the time it was installed.
+    pub sha256: String,
+
+    /// The file in the version's own install directory that `path` links
+    /// to, if it's a link.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<PathBuf>,
+}
+
+/// The on-disk store of installed package receipts.
+///
+/// Each receipt lives in its own `receipts/{name}.toml` file in the project
+/// data directory.
+pub struct Receipts {
+    dir: PathBuf,
+}
+
+impl Receipts {
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            dir: get_project_dirs()?.data_dir().join("receipts"),
+        })
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        check_name(name)?;
+        Ok(self.dir.join(format!("{name}.toml")))
+    }
+
+    /// Gets the receipt for the package called `name`, if it's installed.
+    pub async fn get(&self, name: &str) -> Result<Option<Receipt>> {
+        let path = self.path(name)?;
+        if !fs::try_exists(&path).await.into_diagnostic()? {
+            return Ok(None);
+        }
+
+        Ok(Some(Receipt::read(&path).await?))
+    }
+
+    /// Gets the receipt for `package`, which may be qualified with an index
+    /// like `internal/deploy-cli`, failing if it isn't installed.
+    pub async fn installed(&self, package: &str) -> Result<Receipt> {
+        match self.get(split_qualified(package).1).await? {
+            Some(receipt) => Ok(receipt),
+            None => Err(miette!("Package {} is not installed", package.bold())),
+        }
+    }
+
+    /// Lists the receipts of every installed package, sorted by name.
+    pub async fn list(&self) -> Result<Vec<Receipt>> {
+        if !fs::try_exists(&self.dir).await.into_diagnostic()? {
+            return Ok(Vec::new());
+        }
+
+        let mut receipts = Vec::new();
+        let mut entries = fs::read_dir(&self.dir).await.into_diagnostic()?;
+        while let Some(entry) = entries.next_entry().await.into_diagnostic()? {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                receipts.push(Receipt::read(&path).await?);
+            }
+        }
+        receipts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(receipts)
+    }
+
+    /// Saves `receipt`, replacing any existing receipt for the same package.
+    pub async fn save(&self, receipt: &Receipt) -> Result<()> {
+        fs::create_dir_all(&self.dir).await.into_diagnostic()?;
+        receipt.write(&self.path(&receipt.name)?).await
+    }
+
+    /// Deletes the receipt for the package called `name`.
+    pub async fn remove(&self, name: &str) -> Result<()> {
+        let path = self.path(name)?;
+        if fs::try_exists(&path).await.into_diagnostic()? {
+            fs::remove_file(path).await.into_diagnostic()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receipts_stay_in_their_directory() {
+        let receipts = Receipts {
+            dir: PathBuf::from("/data/receipts"),
+        };
+        assert_eq!(
+            receipts.path("ripgrep").unwrap(),
+            Path::new("/data/receipts/ripgrep.toml")
+        );
+        for name in ["..", "../..", "../config", "a/b", ""] {
+            assert!(receipts.path(name).is_err(), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn installed_packages_can_be_named_with_their_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let receipts = Receipts {
+            dir: dir.path().to_path_buf(),
+        };
+        let receipt = Receipt {
+            name: "deploy-cli".to_string(),
+            index: Some("internal".to_string()),
+            repo: ["acme".to_string(), "deploy-cli".to_string()],
+            tag: "v1.0.0".to_string(),
+            asset_name: "deploy-cli.tar.gz".to_string(),
+            asset_id: 1,
+            download_url: "https://example.com/deploy-cli.tar.gz".to_string(),
+            asset_sha256: "0".repeat(64),
+            previous: Vec::new(),
+            files: Vec::new(),
+        };
+        receipts.save(&receipt).await.unwrap();
+
+        for package in ["deploy-cli", "internal/deploy-cli"] {
+            assert_eq!(receipts.installed(package).await.unwrap().name, "deploy-cli");
+        }
+        let error = receipts.installed("internal/other").await.unwrap_err();
+        assert!(error.to_string().contains("is not installed"), "{error}");
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use index_client::{metadata::check_name, resolver::parse_tag_version};
+use log::info;
+use miette::{IntoDiagnostic, Result};
+use tempfile::TempDir;
+use tokio::fs;
+
+use crate::{
+    commands::uninstall::remove_installed_files,
+    dirs::get_project_dirs,
+    receipts::{Receipt, Receipts},
+};
+
+/// The name of the receipt kept in each version's install directory.
+const RECEIPT_FILE: &str = "receipt.toml";
+
+/// The versions of a package that are installed side by side.
+///
+/// Each version lives in its own `versions/{name}/{tag}` directory in the
+/// project data directory, laid out like the data directory itself, and the
+/// active version's files are linked into place from there.
+pub struct InstalledVersions {
+    dir: PathBuf,
+}
+
+impl InstalledVersions {
+    pub fn open(package: &str) -> Result<Self> {
+        check_name(package)?;
+        Ok(Self {
+            dir: get_project_dirs()?.data_dir().join("versions").join(package),
+        })
+    }
+
+    /// The directory every version is installed in.
+    pub fn root(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The directory the version tagged `tag` is installed in.
+    pub fn dir(&self, tag: &str) -> PathBuf {
+        self.dir.join(tag.replace(['/', '\\'], "_"))
+    }
+
+    /// Gets the receipt of the version tagged `tag`, if it's installed.
+    pub async fn get(&self, tag: &str) -> Result<Option<Receipt>> {
+        let path = self.dir(tag).join(RECEIPT_FILE);
+        if !fs::try_exists(&path).await.into_diagnostic()? {
+            return Ok(None);
+        }
+        Ok(Some(Receipt::read(&path).await?))
+    }
+
+    /// Lists the receipts of every installed version, oldest first.
+    pub async fn list(&self) -> Result<Vec<Receipt>> {
+        if !fs::try_exists(&self.dir).await.into_diagnostic()? {
+            return Ok(Vec::new());
+        }
+
+        let mut versions = Vec::new();
+        let mut entries = fs::read_dir(&self.dir).await.into_diagnostic()?;
+        while let Some(entry) = entries.next_entry().await.into_diagnostic()? {
+            let path = entry.path().join(RECEIPT_FILE);
+            if fs::try_exists(&path).await.into_diagnostic()? {
+                versions.push(Receipt::read(&path).await?);
+            }
+        }
+        versions.sort_by(|a, b| match (parse_tag_version(&a.tag), parse_tag_version(&b.tag)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.tag.cmp(&b.tag),
+        });
+        Ok(versions)
+    }
+
+    /// Creates an empty directory to install a version into, next to the
+    /// version directories so it can be moved into place by
+    /// [`InstalledVersions::install`].
+    pub async fn staging_dir(&self) -> Result<TempDir> {
+        fs::create_dir_all(&self.dir).await.into_diagnostic()?;
+        tempfile::Builder::new()
+            .prefix(".staging-")
+            .tempdir_in(&self.dir)
+            .into_diagnostic()
+    }
+
+    /// Saves the receipt of a version fully installed into `staging`, then
+    /// moves it into place, replacing any existing install of the same tag.
+    ///
+    /// The existing install is only removed once the new one is in place, so
+    /// a failed install never leaves a version half written.
+    pub async fn install(&self, staging: TempDir, receipt: &Receipt) -> Result<()> {
+        receipt.write(&staging.path().join(RECEIPT_FILE)).await?;
+
+        let dir = self.dir(&receipt.tag);
+        let replaced = tempfile::Builder::new()
+            .prefix(".replaced-")
+            .tempdir_in(&self.dir)
+            .into_diagnostic()?;
+        let old_dir = replaced.path().join("version");
+        let has_old = fs::try_exists(&dir).await.into_diagnostic()?;
+        if has_old {
+            fs::rename(&dir, &old_dir).await.into_diagnostic()?;
+        }
+        if let Err(err) = fs::rename(staging.path(), &dir).await {
+            if has_old {
+                fs::rename(&old_dir, &dir).await.into_diagnostic()?;
+            }
+            return Err(err).into_diagnostic();
+        }
+        // The staging directory has been moved, so there's nothing left for
+        // it to clean up.
+        let _ = staging.into_path();
+        Ok(())
+    }
+
+    /// Deletes the version tagged `tag` and everything installed with it.
+    pub async fn remove(&self, tag: &str) -> Result<()> {
+        let dir = self.dir(tag);
+        if fs::try_exists(&dir).await.into_diagnostic()? {
+            fs::remove_dir_all(dir).await.into_diagnostic()?;
+        }
+        Ok(())
+    }
+
+    /// Deletes every installed version.
+    pub async fn remove_all(&self) -> Result<()> {
+        if fs::try_exists(&self.dir).await.into_diagnostic()? {
+            fs::remove_dir_all(&self.dir).await.into_diagnostic()?;
+        }
+        Ok(())
+    }
+}
+
+/// Makes the version of a package described by `receipt` the active one.
+///
+/// Each of its files is linked into place from its install directory, files
+/// that only the previously active version had are removed, and any versions
+/// beyond the `keep` most recently active ones are deleted.
+pub async fn activate(receipts: &Receipts, mut receipt: Receipt, keep: usize) -> Result<Receipt> {
+    let versions = InstalledVersions::open(&receipt.name)?;
+    for file in &receipt.files {
+        if let Some(target) = &file.target {
+            link(target, &file.path).await?;
+        }
+    }
+
+    let previous = receipts.get(&receipt.name).await?;
+    receipt.previous = match &previous {
+        Some(previous) => {
+            let mut history = previous.previous.clone();
+            // Versions installed before they were kept side by side can't be
+            // switched back to.
+            if previous.tag != receipt.tag && versions.get(&previous.tag).await?.is_some() {
+                history.push(previous.tag.clone());
+            }
+            history.retain(|tag| tag != &receipt.tag);
+            history
+        }
+        None => Vec::new(),
+    };
+    let forgotten = receipt.previous.len().saturating_sub(keep);
+    receipt.previous.drain(..forgotten);
+    receipts.save(&receipt).await?;
+
+    if let Some(mut previous) = previous {
+        previous.files.retain(|old| !receipt.owns(&old.path));
+        remove_installed_files(&previous, false).await?;
+    }
+
+    for version in versions.list().await? {
+        if version.tag != receipt.tag && !receipt.previous.contains(&version.tag) {
+            info!(
+                "Removing {} {}, as only {keep} previous versions are kept.",
+                receipt.name.bold(),
+                version.tag.bold()
+            );
+            versions.remove(&version.tag).await?;
+        }
+    }
+
+    Ok(receipt)
+}
+
+/// Points `link` at `target`, replacing whatever is at `link` already.
+async fn link(target: &Path, link: &Path) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent).await.into_diagnostic()?;
+    }
+    if fs::symlink_metadata(link).await.is_ok() {
+        fs::remove_file(link).await.into_diagnostic()?;
+    }
+
+    #[cfg(unix)]
+    fs::symlink(target, link).await.into_diagnostic()?;
+
+    // Creating symlinks needs special privileges on Windows, so the file is
+    // copied instead.
+    #[cfg(not(unix))]
+    fs::copy(target, link).await.into_diagnostic()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_stay_in_their_directory() {
+        for name in ["..", "../..", "../receipts", "a/b", ""] {
+            assert!(InstalledVersions::open(name).is_err(), "{name}");
+        }
+    }
+}
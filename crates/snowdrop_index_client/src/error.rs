@@ -1,4 +1,4 @@
-use std::num::ParseIntError;
+use std::{io, num::ParseIntError, path::PathBuf};
 
 use miette::Diagnostic;
 use reqwest::StatusCode;
@@ -15,18 +15,75 @@ pub enum IndexClientError {
     ))]
     StatusCodeNotOk(StatusCode),
 
+    #[error("`{0}` isn't a valid index URL")]
+    #[diagnostic(help("Local indexes can be given as a path or a `file://` URL"))]
+    InvalidIndexUrl(String),
+
+    #[error("No index found at `{}`", .0.display())]
+    #[diagnostic(help("Make sure `index` in your config points at a checkout of the index"))]
+    LocalIndexNotFound(PathBuf),
+
+    #[error("Failed to read `{}` from the local index", path.display())]
+    LocalReadError {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("The index has no `{0}`")]
+    #[diagnostic(help("Make sure `index` in your config points at a Snowdrop index"))]
+    IndexFileMissing(String),
+
+    #[error("Failed to parse `{location}` from the index")]
+    InvalidIndexFile {
+        location: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to connect to the `{index}` index")]
+    IndexUnavailable {
+        index: String,
+        #[source]
+        source: Box<IndexClientError>,
+    },
+
+    #[error("The `{0}` index is unavailable")]
+    #[diagnostic(help("Connecting to it failed, see the warning above for why"))]
+    IndexSkipped(String),
+
+    #[error("No index named `{index}` is configured")]
+    #[diagnostic(help("The configured indexes are {known}"))]
+    UnknownIndex { index: String, known: String },
+
     #[error("Failed to initialize TLS backend")]
     TlsBackendInitError,
 
+    #[error("`{0}` isn't a valid package name")]
+    #[diagnostic(help("Package names can't be empty, `.` or `..`, or contain path separators"))]
+    InvalidPackageName(String),
+
     #[error("Package not found")]
     PackageNotFound,
 
     #[error("Failed to get latest GitHub Release for repo")]
     GitHubReleaseError(#[from] octocrab::Error),
 
-    #[error("Expected protocol version {0}, got version {1}")]
+    #[error("No release tagged `{tag}` was found")]
+    #[diagnostic(help("Some nearby tags are: {nearby}"))]
+    ReleaseNotFound { tag: String, nearby: String },
+
+    #[error("No release matches the version requirement `{0}`")]
+    #[diagnostic(help("Make sure the requirement matches one of the repo's releases"))]
+    NoMatchingRelease(String),
+
+    #[error("The index uses protocol version {version}, which is newer than Snowdrop supports ({max})")]
     #[diagnostic(help("Try updating Snowdrop to the latest version"))]
-    ProtocolVersionMismatch(u8, u8),
+    IndexTooNew { version: u8, max: u8 },
+
+    #[error("The index uses protocol version {version}, which is older than Snowdrop supports ({min})")]
+    #[diagnostic(help("The index is out of date, try contacting its maintainers"))]
+    IndexTooOld { version: u8, min: u8 },
 
     #[error("Failed to parse protocol version")]
     ProtocolVersionParseError(#[from] ParseIntError),
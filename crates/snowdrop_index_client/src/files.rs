@@ -0,0 +1,30 @@
+use std::{io, path::Path};
+
+use tokio::fs;
+
+/// Replaces the file at `path` with `contents`.
+///
+/// The contents are written to a temporary file next to `path` first, then
+/// renamed over it, so a crash or a concurrent run never sees a half-written
+/// file.
+pub async fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    fs::write(&temp_path, contents).await?;
+    fs::rename(&temp_path, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn files_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("receipt.toml");
+        write_atomically(&path, "first").await.unwrap();
+        write_atomically(&path, "second").await.unwrap();
+        assert_eq!(fs::read_to_string(&path).await.unwrap(), "second");
+        assert!(!dir.path().join("receipt.toml.tmp").exists());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    fmt::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::files::write_atomically;
+
+/// An on-disk cache of the responses of HTTP indexes.
+///
+/// Responses younger than the TTL are served without touching the network,
+/// and older ones are revalidated with their `ETag` or `Last-Modified`
+/// headers. The cache is only ever an optimisation, so failing to read or
+/// write it is never an error.
+#[derive(Clone, Debug)]
+pub struct HttpCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+/// A cached response, stored as one JSON file per URL.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct CachedResponse {
+    /// When the response was last fetched or revalidated, in seconds since
+    /// the Unix epoch.
+    fetched_at: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// The body of the response, or `None` if the index didn't have the
+    /// file.
+    pub body: Option<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+impl CachedResponse {
+    pub fn new(etag: Option<String>, last_modified: Option<String>, body: Option<String>) -> Self {
+        Self {
+            fetched_at: now(),
+            etag,
+            last_modified,
+            body,
+        }
+    }
+
+    /// Marks the response as having just been revalidated.
+    pub fn refreshed(self) -> Self {
+        Self {
+            fetched_at: now(),
+            ..self
+        }
+    }
+}
+
+impl HttpCache {
+    /// Creates a cache stored in `dir`, whose responses are used without
+    /// revalidation for `ttl`.
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// The file the response for `url` is stored in. URLs are
+    /// percent-encoded into a single file name, so every URL gets its own
+    /// file and none of them can escape the cache directory.
+    fn path(&self, url: &str) -> PathBuf {
+        let mut name = String::with_capacity(url.len());
+        for byte in url.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.' {
+                name.push(byte as char);
+            } else {
+                write!(name, "%{byte:02X}").unwrap();
+            }
+        }
+        self.dir.join(name)
+    }
+
+    pub(crate) async fn get(&self, url: &str) -> Option<CachedResponse> {
+        let contents = fs::read(self.path(url)).await.ok()?;
+        match serde_json::from_slice(&contents) {
+            Ok(response) => Some(response),
+            Err(err) => {
+                debug!("Ignoring unreadable cached response for `{url}`: {err}");
+                None
+            }
+        }
+    }
+
+    /// Whether a cached response can be used without revalidating it.
+    pub(crate) fn is_fresh(&self, response: &CachedResponse) -> bool {
+        now().saturating_sub(response.fetched_at) < self.ttl.as_secs()
+    }
+
+    pub(crate) async fn insert(&self, url: &str, response: &CachedResponse) {
+        let path = self.path(url);
+        let result = async {
+            fs::create_dir_all(&self.dir).await?;
+            write_atomically(&path, serde_json::to_vec(response)?).await
+        }
+        .await;
+        if let Err(err) = result {
+            debug!("Failed to cache the response for `{url}`: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(ttl: u64) -> (tempfile::TempDir, HttpCache) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HttpCache::new(dir.path().join("index"), Duration::from_secs(ttl));
+        (dir, cache)
+    }
+
+    #[test]
+    fn urls_are_encoded_into_single_file_names() {
+        let (_dir, cache) = cache(60);
+        let path = cache.path("https://example.com/index/packages/bat.json");
+        assert_eq!(path.parent(), Some(cache.dir.as_path()));
+        assert_eq!(
+            path.file_name().unwrap(),
+            "https%3A%2F%2Fexample.com%2Findex%2Fpackages%2Fbat.json"
+        );
+
+        // URLs that only differ in characters that get encoded still get
+        // their own files, and nothing can climb out of the directory.
+        assert_ne!(cache.path("https://a.b/c_d"), cache.path("https://a.b/c%5Fd"));
+        assert_eq!(cache.path("../../etc/passwd").parent(), Some(cache.dir.as_path()));
+    }
+
+    #[test]
+    fn responses_are_fresh_for_the_ttl() {
+        let (_dir, fresh) = cache(60);
+        let (_dir, stale) = cache(0);
+        let response = CachedResponse::new(None, None, Some("[]".to_string()));
+        assert!(fresh.is_fresh(&response));
+        assert!(!stale.is_fresh(&response));
+
+        let old = CachedResponse {
+            fetched_at: now() - 120,
+            ..response
+        };
+        assert!(!fresh.is_fresh(&old));
+        assert!(fresh.is_fresh(&old.refreshed()));
+    }
+
+    #[tokio::test]
+    async fn responses_round_trip() {
+        let (_dir, cache) = cache(60);
+        let url = "https://example.com/index/names.json";
+        assert!(cache.get(url).await.is_none());
+
+        let response = CachedResponse::new(
+            Some("\"abc\"".to_string()),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            Some("[\"bat\"]".to_string()),
+        );
+        cache.insert(url, &response).await;
+        let cached = cache.get(url).await.unwrap();
+        assert_eq!(cached.fetched_at, response.fetched_at);
+        assert_eq!(cached.etag, response.etag);
+        assert_eq!(cached.last_modified, response.last_modified);
+        assert_eq!(cached.body, response.body);
+    }
+
+    #[tokio::test]
+    async fn missing_files_and_unreadable_entries_are_cached_as_such() {
+        let (_dir, cache) = cache(60);
+        let url = "https://example.com/index/packages/fd.json";
+        cache.insert(url, &CachedResponse::new(None, None, None)).await;
+        assert!(cache.get(url).await.unwrap().body.is_none());
+
+        fs::write(cache.path(url), "not json").await.unwrap();
+        assert!(cache.get(url).await.is_none());
+    }
+}
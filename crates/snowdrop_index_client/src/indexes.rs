@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+
+use log::{debug, warn};
+use miette::Result;
+use secrecy::SecretString;
+
+use crate::{error::IndexClientError, http_cache::HttpCache, metadata::PackageMetadata, IndexClient};
+
+/// Several package indexes, searched in priority order.
+///
+/// Packages can be looked up by name, in which case the highest priority
+/// index that has the package is used, or qualified with the name of an
+/// index, like `internal/deploy-cli`. Indexes that can't be reached are
+/// skipped for lookups by name, but not when they're asked for by name.
+pub struct Indexes {
+    clients: Vec<(String, IndexClient)>,
+    unavailable: Vec<String>,
+}
+
+/// Splits a package name qualified with an index, like `internal/deploy-cli`,
+/// into the index and the package name.
+pub fn split_qualified(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('/') {
+        Some((index, package)) => (Some(index), package),
+        None => (None, name),
+    }
+}
+
+impl Indexes {
+    /// Connects to every index in `indexes`, given as `(name, root)` pairs
+    /// from highest to lowest priority. Responses from HTTP indexes are
+    /// cached in `cache` if it's given.
+    ///
+    /// Indexes that can't be connected to are reported and skipped, unless
+    /// none of them can be.
+    pub async fn new(
+        indexes: &[(String, String)],
+        user_version: &str,
+        pat: SecretString,
+        cache: Option<HttpCache>,
+    ) -> Result<Self, IndexClientError> {
+        let mut clients = Vec::new();
+        let mut unavailable = Vec::new();
+        let mut first_error = None;
+        for (name, root) in indexes {
+            debug!("Connecting to index `{name}` at `{root}`");
+            match IndexClient::new(root, user_version, pat.clone(), cache.clone()).await {
+                Ok(client) => clients.push((name.clone(), client)),
+                Err(source) => {
+                    warn!("Skipping the `{name}` index, as connecting to it failed: {source}");
+                    unavailable.push(name.clone());
+                    first_error.get_or_insert(IndexClientError::IndexUnavailable {
+                        index: name.clone(),
+                        source: Box::new(source),
+                    });
+                }
+            }
+        }
+        match first_error {
+            Some(err) if clients.is_empty() => Err(err),
+            _ => Ok(Self { clients, unavailable }),
+        }
+    }
+
+    /// Whether an index named `name` is configured, even if it couldn't be
+    /// connected to.
+    pub fn contains(&self, name: &str) -> bool {
+        self.names().any(|index| index == name) || self.unavailable.iter().any(|index| index == name)
+    }
+
+    fn get(&self, name: &str) -> Result<&IndexClient, IndexClientError> {
+        if self.unavailable.iter().any(|index| index == name) {
+            return Err(IndexClientError::IndexSkipped(name.to_string()));
+        }
+        self.clients
+            .iter()
+            .find(|(index, _)| index == name)
+            .map(|(_, client)| client)
+            .ok_or_else(|| IndexClientError::UnknownIndex {
+                index: name.to_string(),
+                known: self
+                    .names()
+                    .map(|index| format!("`{index}`"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+
+    /// Gets a package by name, or from a specific index if the name is
+    /// qualified with one.
+    ///
+    /// If several indexes have a package with an unqualified name, the one
+    /// from the highest priority index is used and the others are reported.
+    /// Indexes that fail to look the package up are reported and skipped; if
+    /// none of the others has it, the first failure is returned.
+    pub async fn get_package(&self, name: &str) -> Result<PackageMetadata, IndexClientError> {
+        if let (Some(index), package) = split_qualified(name) {
+            let mut metadata = self.get(index)?.get_package(package).await?;
+            metadata.index = index.to_string();
+            return Ok(metadata);
+        }
+
+        let mut found = Vec::new();
+        let mut first_error = None;
+        for (index, client) in &self.clients {
+            match client.get_package(name).await {
+                Ok(metadata) => found.push((index, metadata)),
+                Err(IndexClientError::PackageNotFound) => {}
+                Err(err) => {
+                    warn!("Skipping the `{index}` index, as looking up `{name}` in it failed: {err}");
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        let mut found = found.into_iter();
+        let Some((index, mut metadata)) = found.next() else {
+            return Err(first_error.unwrap_or(IndexClientError::PackageNotFound));
+        };
+        let shadowed = found.map(|(index, _)| index.as_str()).collect::<Vec<_>>();
+        if let Some(other) = shadowed.first() {
+            warn!(
+                "`{name}` is in the `{index}` index and also in {}, using the one from `{index}`. Use `{other}/{name}` to pick another.",
+                shadowed.iter().map(|index| format!("`{index}`")).collect::<Vec<_>>().join(", ")
+            );
+        }
+        metadata.index = index.clone();
+        Ok(metadata)
+    }
+
+    /// Gets the names of the packages in every index, each with the indexes
+    /// that have it from highest to lowest priority.
+    ///
+    /// Indexes whose names can't be fetched are reported and skipped, unless
+    /// none of them can be.
+    pub async fn get_names(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut names = BTreeMap::<String, Vec<String>>::new();
+        let mut first_error = None;
+        for (index, client) in &self.clients {
+            match client.get_names().await {
+                Ok(index_names) => {
+                    for name in index_names {
+                        names.entry(name).or_default().push(index.clone());
+                    }
+                }
+                Err(err) => {
+                    warn!("Skipping the `{index}` index, as fetching its package names failed: {err}");
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        match first_error {
+            Some(err) if names.is_empty() => Err(err),
+            _ => Ok(names),
+        }
+    }
+
+    /// The names of the indexes, from highest to lowest priority.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clients.iter().map(|(index, _)| index.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::tests::{package, write_index};
+
+    async fn connect(indexes: &[(&str, &str)]) -> Result<Indexes, IndexClientError> {
+        let indexes = indexes
+            .iter()
+            .map(|(name, root)| (name.to_string(), root.to_string()))
+            .collect::<Vec<_>>();
+        Indexes::new(&indexes, "0.0.0", SecretString::new(String::new()), None).await
+    }
+
+    fn root(index: &TempDir) -> &str {
+        index.path().to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn higher_priority_indexes_win() {
+        let internal = write_index("4", &[("deploy-cli", &package("deploy-cli")), ("bat", &package("bat"))]);
+        let default = write_index("4", &[("bat", &package("bat")), ("fd", &package("fd"))]);
+        let indexes = connect(&[("internal", root(&internal)), ("default", root(&default))])
+            .await
+            .unwrap();
+
+        assert_eq!(indexes.get_package("bat").await.unwrap().index, "internal");
+        assert_eq!(indexes.get_package("fd").await.unwrap().index, "default");
+        assert_eq!(indexes.get_package("default/bat").await.unwrap().index, "default");
+        assert!(matches!(
+            indexes.get_package("default/deploy-cli").await,
+            Err(IndexClientError::PackageNotFound)
+        ));
+        assert!(matches!(
+            indexes.get_package("other/bat").await,
+            Err(IndexClientError::UnknownIndex { .. })
+        ));
+
+        let names = indexes.get_names().await.unwrap();
+        assert_eq!(names["bat"], ["internal", "default"]);
+        assert_eq!(names["deploy-cli"], ["internal"]);
+    }
+
+    #[tokio::test]
+    async fn unavailable_indexes_are_skipped() {
+        let default = write_index("4", &[("bat", &package("bat"))]);
+        let missing = default.path().join("missing");
+        let indexes = connect(&[("internal", missing.to_str().unwrap()), ("default", root(&default))])
+            .await
+            .unwrap();
+
+        assert!(indexes.contains("internal"));
+        assert_eq!(indexes.get_package("bat").await.unwrap().index, "default");
+        assert_eq!(indexes.get_names().await.unwrap().len(), 1);
+        assert!(matches!(
+            indexes.get_package("internal/bat").await,
+            Err(IndexClientError::IndexSkipped(index)) if index == "internal"
+        ));
+
+        assert!(matches!(
+            connect(&[("internal", missing.to_str().unwrap())]).await,
+            Err(IndexClientError::IndexUnavailable { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn failing_lookups_are_skipped() {
+        let internal = write_index("4", &[("bat", "{ not json")]);
+        let default = write_index("4", &[("bat", &package("bat"))]);
+        let indexes = connect(&[("internal", root(&internal)), ("default", root(&default))])
+            .await
+            .unwrap();
+        assert_eq!(indexes.get_package("bat").await.unwrap().index, "default");
+
+        // With nothing else to fall back on, the failure is what's reported.
+        let indexes = connect(&[("internal", root(&internal))]).await.unwrap();
+        assert!(matches!(
+            indexes.get_package("bat").await,
+            Err(IndexClientError::InvalidIndexFile { .. })
+        ));
+    }
+}
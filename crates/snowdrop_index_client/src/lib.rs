@@ -1,94 +1,261 @@
-use log::debug;
+use log::{debug, warn};
 use miette::Result;
-use reqwest::{Client, StatusCode};
 use secrecy::SecretString;
 
 pub mod error;
+pub mod files;
+pub mod http_cache;
+pub mod indexes;
 pub mod metadata;
+pub mod resolver;
+pub mod transport;
 use error::IndexClientError;
+use http_cache::HttpCache;
 use metadata::PackageMetadata;
+use transport::Transport;
 
-pub const CURRENT_PROTOCOL_VERSION: u8 = 3;
+/// The newest protocol version the client understands.
+pub const CURRENT_PROTOCOL_VERSION: u8 = 4;
+
+/// The oldest protocol version the client understands.
+pub const MIN_PROTOCOL_VERSION: u8 = 3;
 
 pub struct IndexClient {
-    client: Client,
+    transport: Transport,
     pub index: String,
+    /// The protocol version the index's files are decoded as.
+    pub proto_version: u8,
     pat: SecretString,
 }
 
+/// Works out which protocol version to read an index as from its
+/// `proto_version` file.
+///
+/// The file holds the index's protocol version, optionally followed by the
+/// oldest version it's still backward-compatible with, so that clients that
+/// only know that older version can keep reading it.
+fn negotiate(index: &str, proto_version: &str) -> Result<u8, IndexClientError> {
+    let mut fields = proto_version.split_whitespace();
+    let version: u8 = fields.next().unwrap_or_default().parse()?;
+    let compatible: u8 = match fields.next() {
+        Some(compatible) => compatible.parse()?,
+        None => version,
+    };
+    debug!("Parsed proto version: {version} (compatible with {compatible})");
+
+    if version < MIN_PROTOCOL_VERSION {
+        return Err(IndexClientError::IndexTooOld {
+            version,
+            min: MIN_PROTOCOL_VERSION,
+        });
+    }
+    if version <= CURRENT_PROTOCOL_VERSION {
+        return Ok(version);
+    }
+    if compatible <= CURRENT_PROTOCOL_VERSION {
+        warn!(
+            "The index at `{index}` uses protocol version {version}, which is newer than this version of Snowdrop supports ({CURRENT_PROTOCOL_VERSION}). It's still compatible, but some package information may be ignored until Snowdrop is updated."
+        );
+        return Ok(CURRENT_PROTOCOL_VERSION);
+    }
+    debug!("Proto version being used by Snowdrop ({CURRENT_PROTOCOL_VERSION}) is older than the index server can serve ({compatible}), bailing out...");
+    Err(IndexClientError::IndexTooNew {
+        version,
+        max: CURRENT_PROTOCOL_VERSION,
+    })
+}
+
 impl IndexClient {
-    pub async fn new(index: &String, user_version: &str, pat: SecretString) -> Result<Self, IndexClientError> {
-        let Ok(client) = Client::builder()
-            .user_agent(format!(
-                "SnowdropIndexClient/{} SnowdropCLI/{user_version}",
-                env!("CARGO_PKG_VERSION")
-            ))
-            .build() else {
-                return Err(IndexClientError::TlsBackendInitError)
-            };
-        let proto_version = client
-            .get(format!("{index}/proto_version"))
-            .send()
-            .await?
-            .text()
-            .await?
-            .trim()
-            .parse()?;
-        debug!("Parsed proto version: {proto_version}");
-
-        if proto_version != CURRENT_PROTOCOL_VERSION {
-            debug!("Proto version being used by Snowdrop ({CURRENT_PROTOCOL_VERSION}) != index server proto version ({proto_version}), bailing out...");
-            return Err(IndexClientError::ProtocolVersionMismatch(
-                CURRENT_PROTOCOL_VERSION,
-                proto_version,
-            ));
-        }
+    pub async fn new(
+        index: &String,
+        user_version: &str,
+        pat: SecretString,
+        cache: Option<HttpCache>,
+    ) -> Result<Self, IndexClientError> {
+        let transport = Transport::new(index, user_version, cache)?;
+        let Some(proto_version) = transport.fetch("proto_version").await? else {
+            return Err(IndexClientError::IndexFileMissing(transport.location("proto_version")));
+        };
+        let proto_version = negotiate(index, &String::from_utf8_lossy(&proto_version))?;
 
         Ok(Self {
-            client,
+            transport,
             index: index.to_string(),
+            proto_version,
             pat,
         })
     }
 
     pub async fn get_package(&self, name: &str) -> Result<PackageMetadata, IndexClientError> {
-        let index = &self.index;
-        let endpoint = format!("{index}/packages/{name}.json");
-        log::debug!("Index server endpoint for package `{name}` is `{endpoint}`");
-
-        let http_response = self
-            .client
-            .get(endpoint)
-            .send()
-            .await
-            .map_err(IndexClientError::RequestError)?;
-
-        if let Err(err) = http_response.error_for_status_ref() {
-            if err.status() == Some(StatusCode::NOT_FOUND) {
-                debug!("The index server returned a 404, quitting...");
-                return Err(IndexClientError::PackageNotFound);
-            }
-            return Err(IndexClientError::StatusCodeNotOk(err.status().unwrap()));
-        }
+        let path = format!("packages/{name}.json");
+        log::debug!(
+            "Index endpoint for package `{name}` is `{}`",
+            self.transport.location(&path)
+        );
 
-        let mut metadata = http_response.json::<PackageMetadata>().await?;
+        let decode = |contents: &[u8]| PackageMetadata::decode(self.proto_version, contents);
+        let Some(mut metadata) = self.fetch_json(&path, decode).await? else {
+            debug!("The index doesn't have package `{name}`, quitting...");
+            return Err(IndexClientError::PackageNotFound);
+        };
         metadata.pat = Some(self.pat.clone());
         Ok(metadata)
     }
 
     pub async fn get_names(&self) -> Result<Vec<String>> {
-        let index = &self.index;
-        let endpoint = format!("{index}/names.json");
-        log::debug!("Index server endpoint for package name list is `{endpoint}`");
+        let path = "names.json";
+        log::debug!(
+            "Index endpoint for package name list is `{}`",
+            self.transport.location(path)
+        );
 
         Ok(self
-            .client
-            .get(endpoint)
-            .send()
-            .await
-            .map_err(IndexClientError::RequestError)?
-            .json::<Vec<String>>()
+            .fetch_json(path, |contents| serde_json::from_slice::<Vec<String>>(contents))
+            .await?
+            .ok_or_else(|| IndexClientError::IndexFileMissing(self.transport.location(path)))?)
+    }
+
+    async fn fetch_json<T>(
+        &self,
+        path: &str,
+        decode: impl FnOnce(&[u8]) -> serde_json::Result<T>,
+    ) -> Result<Option<T>, IndexClientError> {
+        let Some(contents) = self.transport.fetch(path).await? else {
+            return Ok(None);
+        };
+        decode(&contents)
+            .map(Some)
+            .map_err(|source| IndexClientError::InvalidIndexFile {
+                location: self.transport.location(path),
+                source,
+            })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{fs, path::Path};
+
+    use reqwest::Url;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Writes an index with the given `proto_version` file and packages,
+    /// given as `(name, metadata)` pairs, into a temporary directory.
+    pub(crate) fn write_index(proto_version: &str, packages: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("proto_version"), proto_version).unwrap();
+        let names = packages.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        fs::write(dir.path().join("names.json"), serde_json::to_vec(&names).unwrap()).unwrap();
+        fs::create_dir(dir.path().join("packages")).unwrap();
+        for (name, metadata) in packages {
+            fs::write(dir.path().join("packages").join(format!("{name}.json")), metadata).unwrap();
+        }
+        dir
+    }
+
+    pub(crate) fn package(name: &str) -> String {
+        format!(r#"{{ "name": "{name}", "pretty_name": "{name}", "repo": ["octocat", "{name}"] }}"#)
+    }
+
+    async fn connect(root: &Path) -> Result<IndexClient, IndexClientError> {
+        let root = root.display().to_string();
+        IndexClient::new(&root, "0.0.0", SecretString::new(String::new()), None).await
+    }
+
+    fn negotiated(proto_version: &str) -> Result<u8, IndexClientError> {
+        negotiate("https://example.com/index", proto_version)
+    }
+
+    #[tokio::test]
+    async fn local_indexes_are_read() {
+        let index = write_index("4", &[("ripgrep", &package("ripgrep")), ("bat", &package("bat"))]);
+        let client = connect(index.path()).await.unwrap();
+        assert_eq!(client.proto_version, 4);
+        assert_eq!(client.get_names().await.unwrap(), ["ripgrep", "bat"]);
+
+        let metadata = client.get_package("bat").await.unwrap();
+        assert_eq!(metadata.repo, ["octocat", "bat"]);
+        assert!(metadata.naming_scheme.is_none());
+        assert!(matches!(
+            client.get_package("fd").await,
+            Err(IndexClientError::PackageNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn local_indexes_can_be_given_as_file_urls() {
+        let index = write_index("4", &[("bat", &package("bat"))]);
+        let url = Url::from_directory_path(index.path()).unwrap().to_string();
+        let client = IndexClient::new(&url, "0.0.0", SecretString::new(String::new()), None)
             .await
-            .map_err(IndexClientError::RequestError)?)
+            .unwrap();
+        assert_eq!(client.get_package("bat").await.unwrap().name, "bat");
+    }
+
+    #[tokio::test]
+    async fn broken_local_indexes_are_reported() {
+        let missing = tempfile::tempdir().unwrap().path().join("index");
+        assert!(matches!(
+            connect(&missing).await,
+            Err(IndexClientError::LocalIndexNotFound(_))
+        ));
+
+        let index = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            connect(index.path()).await,
+            Err(IndexClientError::IndexFileMissing(_))
+        ));
+
+        let index = write_index("4", &[("bat", "{ not json")]);
+        let client = connect(index.path()).await.unwrap();
+        assert!(matches!(
+            client.get_package("bat").await,
+            Err(IndexClientError::InvalidIndexFile { .. })
+        ));
+    }
+
+    #[test]
+    fn supported_versions_are_used_as_is() {
+        assert_eq!(negotiated("3").unwrap(), 3);
+        assert_eq!(negotiated("4").unwrap(), 4);
+        assert_eq!(negotiated("4\n").unwrap(), 4);
+        assert_eq!(negotiated("4 3").unwrap(), 4);
+    }
+
+    #[test]
+    fn newer_compatible_versions_are_read_as_the_current_one() {
+        assert_eq!(negotiated("5 4").unwrap(), CURRENT_PROTOCOL_VERSION);
+        assert_eq!(negotiated("7 3").unwrap(), CURRENT_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn unsupported_versions_are_refused() {
+        assert!(matches!(
+            negotiated("5"),
+            Err(IndexClientError::IndexTooNew { version: 5, max: 4 })
+        ));
+        assert!(matches!(
+            negotiated("6 5"),
+            Err(IndexClientError::IndexTooNew { version: 6, max: 4 })
+        ));
+        assert!(matches!(
+            negotiated("2"),
+            Err(IndexClientError::IndexTooOld { version: 2, min: 3 })
+        ));
+    }
+
+    #[test]
+    fn malformed_versions_are_errors() {
+        for proto_version in ["", "x", "4 x", "-1", "300"] {
+            assert!(
+                matches!(
+                    negotiated(proto_version),
+                    Err(IndexClientError::ProtocolVersionParseError(_))
+                ),
+                "{proto_version:?}"
+            );
+        }
     }
 }
@@ -1,19 +1,198 @@
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
 use octocrab::{models::repos::Release, Octocrab};
 use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 
-use crate::error::IndexClientError;
+use crate::{
+    error::IndexClientError,
+    resolver::{self, VersionSpec},
+};
 
+/// A package in the index.
+///
+/// Every field after `repo` was added in protocol version 4, which also made
+/// `naming_scheme` optional.
 #[derive(Deserialize, Debug)]
 pub struct PackageMetadata {
     pub name: String,
     pub pretty_name: String,
     pub repo: [String; 2],
-    pub naming_scheme: String,
+    #[serde(default)]
+    pub naming_scheme: Option<String>,
+    /// A short description of the package.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The package's homepage, if it has one besides its repo.
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// The SPDX identifier of the package's license, like `MIT` or
+    /// `MIT OR Apache-2.0`.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// Tags and categories the package can be found by.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The names of the executables the package provides. This is only
+    /// informational; `bins` decides what's installed.
+    #[serde(default)]
+    pub provides: Vec<String>,
+    /// A glob matching the release asset that holds checksums for the other
+    /// assets, for releases whose checksum files aren't named conventionally.
+    #[serde(default)]
+    pub checksum_asset: Option<String>,
+    /// The key releases are signed with, either as a minisign public key or
+    /// an SSH public key (optionally as an `allowed_signers` line). Packages
+    /// with a key can only be installed from signed releases.
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// A glob matching the release asset that holds the signature, for
+    /// releases whose signatures aren't named conventionally.
+    #[serde(default)]
+    pub signature_asset: Option<String>,
+    /// The executables to install from the release archive. If this is
+    /// empty, the executables are found automatically.
+    #[serde(default)]
+    pub bins: Vec<Bin>,
+    /// Where the files in the release archive should be installed, for
+    /// archives that can't be made sense of automatically.
+    #[serde(default)]
+    pub layout: Option<Layout>,
+    /// The name of the index the package was found in.
+    #[serde(skip)]
+    pub index: String,
     pub(crate) pat: Option<SecretString>,
 }
 
+/// A package as protocol version 3 describes it.
+#[derive(Deserialize)]
+struct PackageMetadataV3 {
+    name: String,
+    pretty_name: String,
+    repo: [String; 2],
+    naming_scheme: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Bin {
+    /// The path of the executable inside the release archive, relative to
+    /// its top-level directory if it has one.
+    pub path: String,
+    /// The name to install the executable as, instead of its file name.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Rules for installing the files in a release archive. Every glob is
+/// matched against paths inside the archive, after any leading directories
+/// have been stripped.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Layout {
+    /// How many leading directories to strip from every path. If this isn't
+    /// set, a single top-level directory wrapping everything is stripped.
+    #[serde(default)]
+    pub strip_components: Option<usize>,
+    /// Globs matching the executables to install, instead of `bins`.
+    #[serde(default)]
+    pub bins: Vec<String>,
+    /// Globs matching man pages, which are installed by their section.
+    #[serde(default)]
+    pub man_pages: Vec<String>,
+    #[serde(default)]
+    pub completions: Completions,
+    /// Globs matching license files.
+    #[serde(default)]
+    pub licenses: Vec<String>,
+    /// Globs matching anything else to install into the package's data
+    /// directory, keeping their paths.
+    #[serde(default)]
+    pub data: Vec<String>,
+    /// Names to install files as, keyed by a glob matching their path.
+    #[serde(default)]
+    pub renames: BTreeMap<String, String>,
+}
+
+/// Globs matching the shell completion scripts for each shell.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Completions {
+    #[serde(default)]
+    pub bash: Vec<String>,
+    #[serde(default)]
+    pub zsh: Vec<String>,
+    #[serde(default)]
+    pub fish: Vec<String>,
+}
+
+impl Bin {
+    /// The name the executable is installed as, without any platform
+    /// specific suffix.
+    pub fn installed_name(&self) -> &str {
+        match &self.name {
+            Some(name) => name,
+            None => self.path.rsplit('/').next().unwrap_or(&self.path),
+        }
+    }
+}
+
+/// Checks that a package name is a single plain path component, as it's used
+/// to name the files and directories the package is stored in.
+pub fn check_name(name: &str) -> Result<(), IndexClientError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(component)), None) if component == name => Ok(()),
+        _ => Err(IndexClientError::InvalidPackageName(name.to_string())),
+    }
+}
+
 impl PackageMetadata {
+    /// Decodes package metadata written for protocol version `version`,
+    /// which must be one the client supports. Names that aren't a single
+    /// path component are rejected, see [`check_name`].
+    pub(crate) fn decode(version: u8, contents: &[u8]) -> serde_json::Result<Self> {
+        let metadata = match version {
+            // Fields added since version 3 are ignored like an older client
+            // would, in case an index sets them without bumping its version.
+            3 => {
+                let metadata: PackageMetadataV3 = serde_json::from_slice(contents)?;
+                Self {
+                    name: metadata.name,
+                    pretty_name: metadata.pretty_name,
+                    repo: metadata.repo,
+                    naming_scheme: Some(metadata.naming_scheme),
+                    description: None,
+                    homepage: None,
+                    license: None,
+                    tags: Vec::new(),
+                    provides: Vec::new(),
+                    checksum_asset: None,
+                    public_key: None,
+                    signature_asset: None,
+                    bins: Vec::new(),
+                    layout: None,
+                    index: String::new(),
+                    pat: None,
+                }
+            }
+            4 => serde_json::from_slice(contents)?,
+            _ => unreachable!("protocol version {version} isn't supported"),
+        };
+        check_name(&metadata.name).map_err(serde::de::Error::custom)?;
+        Ok(metadata)
+    }
+
+    /// The names of the executables the package provides, as the index
+    /// lists them or else as its `bins` install them.
+    pub fn provided_bins(&self) -> Vec<&str> {
+        if self.provides.is_empty() {
+            self.bins.iter().map(Bin::installed_name).collect()
+        } else {
+            self.provides.iter().map(String::as_str).collect()
+        }
+    }
+
     pub async fn get_latest_release(&self) -> Result<Release, IndexClientError> {
         let Some(ref pat) = self.pat else {
             return Err(IndexClientError::NoPat)
@@ -22,6 +201,90 @@ impl PackageMetadata {
 
         Ok(octocrab(pat)?.repos(owner, repo).releases().get_latest().await?)
     }
+
+    /// Gets the release tagged `tag`.
+    ///
+    /// Tags are matched tolerantly, so `13.0.0` will find a release tagged
+    /// `v13.0.0` and vice versa.
+    pub async fn get_release(&self, tag: &str) -> Result<Release, IndexClientError> {
+        let Some(ref pat) = self.pat else {
+            return Err(IndexClientError::NoPat)
+        };
+        let [owner, repo] = &self.repo;
+        let octocrab = octocrab(pat)?;
+
+        for candidate in tag_candidates(tag) {
+            if let Ok(release) = octocrab.repos(owner, repo).releases().get_by_tag(&candidate).await {
+                return Ok(release);
+            }
+        }
+
+        let releases = self.get_releases().await?;
+        if let Some(release) = releases
+            .iter()
+            .find(|release| normalize_tag(&release.tag_name) == normalize_tag(tag))
+        {
+            return Ok(release.clone());
+        }
+
+        let nearby = nearby_tags(tag, &releases);
+        Err(IndexClientError::ReleaseNotFound {
+            tag: tag.to_string(),
+            nearby: if nearby.is_empty() {
+                "(the repo has no releases)".to_string()
+            } else {
+                nearby.join(", ")
+            },
+        })
+    }
+
+    /// Gets the release that `spec` refers to.
+    ///
+    /// Requirements resolve to the highest matching version across every
+    /// release of the repo; prereleases are only considered when
+    /// `include_prereleases` is set.
+    pub async fn get_release_matching(
+        &self,
+        spec: &VersionSpec,
+        include_prereleases: bool,
+    ) -> Result<Release, IndexClientError> {
+        match spec {
+            VersionSpec::Tag(tag) => self.get_release(tag).await,
+            VersionSpec::Requirement(requirement) => {
+                let releases = self.get_releases().await?;
+                resolver::resolve(&releases, requirement, include_prereleases)
+                    .cloned()
+                    .ok_or_else(|| IndexClientError::NoMatchingRelease(requirement.to_string()))
+            }
+        }
+    }
+
+    /// Gets every release of the package's repo, newest first.
+    pub async fn get_releases(&self) -> Result<Vec<Release>, IndexClientError> {
+        let Some(ref pat) = self.pat else {
+            return Err(IndexClientError::NoPat)
+        };
+        let [owner, repo] = &self.repo;
+        let octocrab = octocrab(pat)?;
+
+        let mut releases = Vec::new();
+        for page in 1u32.. {
+            let response = octocrab
+                .repos(owner, repo)
+                .releases()
+                .list()
+                .per_page(100)
+                .page(page)
+                .send()
+                .await?;
+            let is_last_page = response.next.is_none() || response.items.is_empty();
+            releases.extend(response.items);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(releases)
+    }
 }
 
 fn octocrab(pat: &SecretString) -> Result<Octocrab, IndexClientError> {
@@ -29,3 +292,117 @@ fn octocrab(pat: &SecretString) -> Result<Octocrab, IndexClientError> {
         .personal_token(pat.expose_secret().to_string())
         .build()?)
 }
+
+/// Strips the conventional `v` prefix from a tag.
+fn normalize_tag(tag: &str) -> &str {
+    tag.strip_prefix(['v', 'V']).unwrap_or(tag)
+}
+
+/// The tags to try when looking up `tag`: the tag itself, then the tag with
+/// its `v` prefix added or removed.
+fn tag_candidates(tag: &str) -> Vec<String> {
+    let normalized = normalize_tag(tag);
+    let mut candidates = vec![tag.to_string()];
+    for candidate in [normalized.to_string(), format!("v{normalized}")] {
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Picks up to five tags from `releases` that look most like `tag`, so they
+/// can be suggested when `tag` doesn't exist.
+fn nearby_tags(tag: &str, releases: &[Release]) -> Vec<String> {
+    let wanted = normalize_tag(tag);
+    let mut tags = releases
+        .iter()
+        .map(|release| {
+            let shared_prefix = normalize_tag(&release.tag_name)
+                .chars()
+                .zip(wanted.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            (shared_prefix, release.tag_name.clone())
+        })
+        .collect::<Vec<_>>();
+    // `sort_by_key` is stable, so ties keep the newest-first order.
+    tags.sort_by_key(|(shared_prefix, _)| std::cmp::Reverse(*shared_prefix));
+    tags.into_iter().take(5).map(|(_, tag)| tag).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE: &str = r#"{
+        "name": "ripgrep",
+        "pretty_name": "ripgrep",
+        "repo": ["BurntSushi", "ripgrep"],
+        "naming_scheme": "ripgrep-*-{{llvm_triple}}.tar.gz",
+        "description": "Recursively searches directories for a regex pattern",
+        "checksum_asset": "*.sha256",
+        "public_key": "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3",
+        "bins": [{ "path": "rg" }],
+        "layout": { "man_pages": ["doc/rg.1"] }
+    }"#;
+
+    #[test]
+    fn names_must_be_a_single_component() {
+        for name in ["ripgrep", "deploy-cli", "tool.v2", "..tool"] {
+            assert!(check_name(name).is_ok(), "{name}");
+        }
+        for name in ["", ".", "..", "../..", "../tool", "a/b", "/tool", "tool/", "./tool"] {
+            assert!(
+                matches!(check_name(name), Err(IndexClientError::InvalidPackageName(_))),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn version_3_ignores_later_fields() {
+        let metadata = PackageMetadata::decode(3, PACKAGE.as_bytes()).unwrap();
+        assert_eq!(metadata.name, "ripgrep");
+        assert_eq!(metadata.repo, ["BurntSushi", "ripgrep"]);
+        assert_eq!(
+            metadata.naming_scheme.as_deref(),
+            Some("ripgrep-*-{{llvm_triple}}.tar.gz")
+        );
+        assert!(metadata.description.is_none());
+        assert!(metadata.checksum_asset.is_none());
+        assert!(metadata.public_key.is_none());
+        assert!(metadata.bins.is_empty());
+        assert!(metadata.layout.is_none());
+    }
+
+    #[test]
+    fn version_3_requires_a_naming_scheme() {
+        let package = r#"{ "name": "rg", "pretty_name": "ripgrep", "repo": ["BurntSushi", "ripgrep"] }"#;
+        assert!(PackageMetadata::decode(3, package.as_bytes()).is_err());
+        let metadata = PackageMetadata::decode(4, package.as_bytes()).unwrap();
+        assert!(metadata.naming_scheme.is_none());
+    }
+
+    #[test]
+    fn names_that_escape_their_directory_are_rejected() {
+        let package = r#"{ "name": "../../bin", "pretty_name": "x", "repo": ["a", "b"], "naming_scheme": "*" }"#;
+        for version in [3, 4] {
+            let error = PackageMetadata::decode(version, package.as_bytes()).unwrap_err();
+            assert!(error.to_string().contains("isn't a valid package name"), "{error}");
+        }
+    }
+
+    #[test]
+    fn version_4_decodes_every_field() {
+        let metadata = PackageMetadata::decode(4, PACKAGE.as_bytes()).unwrap();
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Recursively searches directories for a regex pattern")
+        );
+        assert_eq!(metadata.checksum_asset.as_deref(), Some("*.sha256"));
+        assert!(metadata.public_key.is_some());
+        assert_eq!(metadata.provided_bins(), ["rg"]);
+        assert_eq!(metadata.layout.unwrap().man_pages, ["doc/rg.1"]);
+    }
+}
@@ -0,0 +1,250 @@
+use octocrab::models::repos::Release;
+use semver::{Prerelease, Version, VersionReq};
+
+/// A request for a particular version of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSpec {
+    /// An exact release tag, such as `v13.0.0`.
+    Tag(String),
+
+    /// A semver requirement, such as `^0.22` or `~1.4`.
+    Requirement(VersionReq),
+}
+
+impl VersionSpec {
+    /// Parses a version string given by the user.
+    ///
+    /// Strings that start with a comparison operator, or that contain
+    /// wildcards or multiple comparators, are treated as semver requirements;
+    /// anything else is treated as an exact tag, so that `13.0.0` means that
+    /// release and not `^13.0.0`.
+    pub fn parse(spec: &str) -> Self {
+        let is_requirement = spec.starts_with(['^', '~', '=', '>', '<', '*'])
+            || spec.contains(',')
+            || spec.split('.').any(|part| matches!(part, "x" | "X" | "*"));
+        if is_requirement {
+            if let Ok(requirement) = VersionReq::parse(spec) {
+                return Self::Requirement(requirement);
+            }
+        }
+        Self::Tag(spec.to_string())
+    }
+}
+
+/// Parses a release tag as a semver version.
+///
+/// This tolerates `v` prefixes (`v1.2.3`), package name prefixes
+/// (`bat-v0.22.1`, `release-1.2.3`) and versions that leave out their minor
+/// or patch components (`1.4`).
+pub fn parse_tag_version(tag: &str) -> Option<Version> {
+    let chars = tag.char_indices().collect::<Vec<_>>();
+    for (position, &(index, c)) in chars.iter().enumerate() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        // Only start a version at the beginning of a "word", optionally after
+        // a `v`, so that `tool2-v1.0` is read as 1.0 and not 2.
+        let starts_word = match position {
+            0 => true,
+            _ => {
+                let previous = chars[position - 1].1;
+                // A `.` means this is the middle of a version that couldn't
+                // be parsed, like the `2` in `1.2.3.4`.
+                !(previous.is_alphanumeric() || previous == '.')
+                    || (matches!(previous, 'v' | 'V') && (position == 1 || !chars[position - 2].1.is_alphanumeric()))
+            }
+        };
+        if starts_word {
+            if let Some(version) = parse_loose_version(&tag[index..]) {
+                return Some(version);
+            }
+        }
+    }
+    None
+}
+
+/// Whether the release tagged `latest` is newer than the one tagged
+/// `installed`.
+///
+/// Tags are compared as versions when both can be parsed, so that a release
+/// installed ahead of the latest one (such as a prerelease) isn't considered
+/// outdated. Otherwise any difference counts as newer.
+pub fn is_newer(latest: &str, installed: &str) -> bool {
+    match (parse_tag_version(latest), parse_tag_version(installed)) {
+        (Some(latest), Some(installed)) => latest > installed,
+        _ => latest != installed,
+    }
+}
+
+fn parse_loose_version(version: &str) -> Option<Version> {
+    if let Ok(version) = Version::parse(version) {
+        return Some(version);
+    }
+
+    // Pad out missing minor and patch components, keeping any suffix.
+    let core_end = version
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(version.len());
+    let (core, suffix) = version.split_at(core_end);
+    let core = core.trim_end_matches('.');
+    let components = core.split('.').count();
+    if core.is_empty() || components > 3 {
+        return None;
+    }
+    Version::parse(&format!("{core}{}{suffix}", ".0".repeat(3 - components))).ok()
+}
+
+/// Picks the release with the highest version matching `requirement`.
+///
+/// Drafts are always skipped, and prereleases (either flagged as such on
+/// GitHub or carrying a semver prerelease) are skipped unless
+/// `include_prereleases` is set. Tags that can't be parsed as versions are
+/// ignored.
+pub fn resolve<'a>(
+    releases: &'a [Release],
+    requirement: &VersionReq,
+    include_prereleases: bool,
+) -> Option<&'a Release> {
+    releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| Some((parse_tag_version(&release.tag_name)?, release)))
+        .filter(|(version, release)| {
+            let is_prerelease = release.prerelease || !version.pre.is_empty();
+            if is_prerelease && !include_prereleases {
+                return false;
+            }
+            requirement.matches(version)
+                || (include_prereleases && {
+                    // `VersionReq` only matches prereleases of the exact
+                    // version it names, so compare against the release the
+                    // prerelease is leading up to instead.
+                    let mut stable = version.clone();
+                    stable.pre = Prerelease::EMPTY;
+                    requirement.matches(&stable)
+                })
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, release)| release)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool, draft: bool) -> Release {
+        let url = "https://api.github.com/x";
+        let user = json!({
+            "login": "octocat", "id": 1, "node_id": "", "avatar_url": url, "gravatar_id": "",
+            "url": url, "html_url": url, "followers_url": url, "following_url": url,
+            "gists_url": url, "starred_url": url, "subscriptions_url": url,
+            "organizations_url": url, "repos_url": url, "events_url": url,
+            "received_events_url": url, "type": "User", "site_admin": false,
+        });
+        serde_json::from_value(json!({
+            "url": url, "html_url": url, "assets_url": url, "upload_url": url,
+            "tarball_url": null, "zipball_url": null, "id": 1, "node_id": "",
+            "tag_name": tag, "target_commitish": "main", "name": null, "body": null,
+            "draft": draft, "prerelease": prerelease, "created_at": null,
+            "published_at": null, "author": user, "assets": [],
+        }))
+        .unwrap()
+    }
+
+    fn resolved<'a>(releases: &'a [Release], requirement: &str, include_prereleases: bool) -> Option<&'a str> {
+        resolve(releases, &VersionReq::parse(requirement).unwrap(), include_prereleases)
+            .map(|release| release.tag_name.as_str())
+    }
+
+    fn version(version: &str) -> Option<Version> {
+        Some(Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn specs_are_tags_unless_they_look_like_requirements() {
+        assert_eq!(VersionSpec::parse("13.0.0"), VersionSpec::Tag("13.0.0".into()));
+        assert_eq!(VersionSpec::parse("v13.0.0"), VersionSpec::Tag("v13.0.0".into()));
+        assert_eq!(VersionSpec::parse("nightly"), VersionSpec::Tag("nightly".into()));
+        for requirement in ["^0.22", "~1.4", "=1.2.3", ">=1, <2", "1.x", "1.*", "*"] {
+            assert_eq!(
+                VersionSpec::parse(requirement),
+                VersionSpec::Requirement(VersionReq::parse(requirement).unwrap()),
+                "{requirement}"
+            );
+        }
+        // Invalid requirements fall back to being tags.
+        assert_eq!(VersionSpec::parse("^nope"), VersionSpec::Tag("^nope".into()));
+    }
+
+    #[test]
+    fn tag_versions_tolerate_prefixes() {
+        assert_eq!(parse_tag_version("13.0.0"), version("13.0.0"));
+        assert_eq!(parse_tag_version("v13.0.0"), version("13.0.0"));
+        assert_eq!(parse_tag_version("V2.1.0"), version("2.1.0"));
+        assert_eq!(parse_tag_version("bat-v0.22.1"), version("0.22.1"));
+        assert_eq!(parse_tag_version("release-1.2.3"), version("1.2.3"));
+        assert_eq!(parse_tag_version("tool2-v1.0"), version("1.0.0"));
+        assert_eq!(parse_tag_version("v1.4"), version("1.4.0"));
+        assert_eq!(parse_tag_version("v1"), version("1.0.0"));
+        assert_eq!(parse_tag_version("v1.5.0-rc.1"), version("1.5.0-rc.1"));
+    }
+
+    #[test]
+    fn tags_without_versions_are_rejected() {
+        assert_eq!(parse_tag_version("nightly"), None);
+        assert_eq!(parse_tag_version("latest"), None);
+        assert_eq!(parse_tag_version("v1.2.3.4"), None);
+        assert_eq!(parse_tag_version(""), None);
+    }
+
+    #[test]
+    fn resolve_picks_the_highest_match() {
+        let releases = [
+            release("v0.21.0", false, false),
+            release("v0.22.1", false, false),
+            release("v0.22.0", false, false),
+            release("v1.0.0", false, false),
+            release("nightly", false, false),
+        ];
+        assert_eq!(resolved(&releases, "^0.22", false), Some("v0.22.1"));
+        assert_eq!(resolved(&releases, "~0.21", false), Some("v0.21.0"));
+        assert_eq!(resolved(&releases, "*", false), Some("v1.0.0"));
+        assert_eq!(resolved(&releases, "^2", false), None);
+    }
+
+    #[test]
+    fn resolve_skips_drafts_and_prereleases() {
+        let releases = [
+            release("v1.0.0", false, false),
+            release("v1.1.0", false, true),
+            release("v1.2.0-beta.1", false, false),
+            release("v1.3.0", true, false),
+        ];
+        assert_eq!(resolved(&releases, "^1", false), Some("v1.0.0"));
+        assert_eq!(resolved(&releases, "^1", true), Some("v1.3.0"));
+        // Prereleases count towards the version they lead up to.
+        assert_eq!(resolved(&releases, "=1.2.0", true), Some("v1.2.0-beta.1"));
+    }
+
+    #[test]
+    fn newer_releases_are_compared_as_versions() {
+        assert!(is_newer("v1.10.0", "v1.9.0"));
+        assert!(is_newer("2.0.0", "v1.9.9"));
+        assert!(!is_newer("v1.9.0", "v1.10.0"));
+        assert!(!is_newer("v1.0.0", "1.0.0"));
+    }
+
+    #[test]
+    fn releases_installed_ahead_of_latest_are_not_outdated() {
+        assert!(!is_newer("v1.4.0", "v1.5.0-rc.1"));
+        assert!(is_newer("v1.5.0", "v1.5.0-rc.1"));
+    }
+
+    #[test]
+    fn unparseable_tags_differ_by_name() {
+        assert!(is_newer("nightly-2023-02-01", "nightly"));
+        assert!(!is_newer("nightly", "nightly"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use log::debug;
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Client, StatusCode, Url,
+};
+
+use crate::{
+    error::IndexClientError,
+    http_cache::{CachedResponse, HttpCache},
+};
+
+/// How the files of an index are fetched. Every transport serves the same
+/// layout, so the protocol doesn't depend on where the index lives.
+pub enum Transport {
+    /// An index served over HTTP(S) from the given root URL, with its
+    /// responses optionally cached.
+    Http {
+        client: Client,
+        root: String,
+        cache: Option<HttpCache>,
+    },
+
+    /// A checkout of the index in a local directory.
+    Local(PathBuf),
+}
+
+impl Transport {
+    /// Picks the transport for an index, which is either an HTTP(S) URL, a
+    /// `file://` URL or a path to a local directory. Only HTTP indexes are
+    /// cached.
+    pub fn new(index: &str, user_version: &str, cache: Option<HttpCache>) -> Result<Self, IndexClientError> {
+        if index.starts_with("http://") || index.starts_with("https://") {
+            let Ok(client) = Client::builder()
+                .user_agent(format!(
+                    "SnowdropIndexClient/{} SnowdropCLI/{user_version}",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .build()
+            else {
+                return Err(IndexClientError::TlsBackendInitError);
+            };
+            return Ok(Self::Http {
+                client,
+                root: index.trim_end_matches('/').to_string(),
+                cache,
+            });
+        }
+
+        let root = if index.starts_with("file://") {
+            Url::parse(index)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .ok_or_else(|| IndexClientError::InvalidIndexUrl(index.to_string()))?
+        } else {
+            PathBuf::from(index)
+        };
+        if !root.is_dir() {
+            return Err(IndexClientError::LocalIndexNotFound(root));
+        }
+        Ok(Self::Local(root))
+    }
+
+    /// Where the file at `path`, relative to the index root, is fetched from.
+    pub fn location(&self, path: &str) -> String {
+        match self {
+            Self::Http { root, .. } => format!("{root}/{path}"),
+            Self::Local(root) => root.join(path).display().to_string(),
+        }
+    }
+
+    /// Fetches the file at `path`, relative to the index root, or `None` if
+    /// the index doesn't have it.
+    pub async fn fetch(&self, path: &str) -> Result<Option<Vec<u8>>, IndexClientError> {
+        debug!("Fetching `{}` from the index", self.location(path));
+        match self {
+            Self::Http { client, root, cache } => fetch_http(client, &format!("{root}/{path}"), cache.as_ref()).await,
+            Self::Local(root) => {
+                let file = root.join(path);
+                match tokio::fs::read(&file).await {
+                    Ok(contents) => Ok(Some(contents)),
+                    Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                    Err(source) => Err(IndexClientError::LocalReadError { path: file, source }),
+                }
+            }
+        }
+    }
+}
+
+async fn fetch_http(
+    client: &Client,
+    url: &str,
+    cache: Option<&HttpCache>,
+) -> Result<Option<Vec<u8>>, IndexClientError> {
+    let cached = match cache {
+        Some(cache) => cache.get(url).await.map(|cached| (cache, cached)),
+        None => None,
+    };
+    if let Some((cache, cached)) = &cached {
+        if cache.is_fresh(cached) {
+            debug!("Using the cached response for `{url}`");
+            return Ok(cached.body.clone().map(String::into_bytes));
+        }
+    }
+
+    let mut request = client.get(url);
+    if let Some((_, cached)) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await?;
+
+    if let Some((cache, cached)) = cached {
+        if response.status() == StatusCode::NOT_MODIFIED {
+            debug!("The cached response for `{url}` is still valid");
+            let cached = cached.refreshed();
+            cache.insert(url, &cached).await;
+            return Ok(cached.body.map(String::into_bytes));
+        }
+    }
+    if response.status() == StatusCode::NOT_FOUND {
+        debug!("The index server returned a 404");
+        if let Some(cache) = cache {
+            cache.insert(url, &CachedResponse::new(None, None, None)).await;
+        }
+        return Ok(None);
+    }
+    if let Err(err) = response.error_for_status_ref() {
+        return Err(IndexClientError::StatusCodeNotOk(err.status().unwrap()));
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+    let body = response.bytes().await?.to_vec();
+    if let (Some(cache), Ok(text)) = (cache, std::str::from_utf8(&body)) {
+        let cached = CachedResponse::new(etag, last_modified, Some(text.to_string()));
+        cache.insert(url, &cached).await;
+    }
+    Ok(Some(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    /// Serves `responses` in order, one per connection, and sends the head of
+    /// every request it gets to the returned receiver.
+    fn serve(responses: Vec<&'static str>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, received) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                while reader.read_line(&mut head).unwrap() > 2 {}
+                requests.send(head.to_lowercase()).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (root, received)
+    }
+
+    fn transport(root: &str, ttl: u64) -> (tempfile::TempDir, Transport) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HttpCache::new(dir.path().to_path_buf(), Duration::from_secs(ttl));
+        (dir, Transport::new(root, "0.0.0", Some(cache)).unwrap())
+    }
+
+    #[tokio::test]
+    async fn stale_responses_are_revalidated() {
+        let (root, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\n\
+             Content-Length: 7\r\nConnection: close\r\n\r\n[\"bat\"]",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+        ]);
+        let (_dir, transport) = transport(&root, 0);
+
+        assert_eq!(transport.fetch("names.json").await.unwrap().unwrap(), b"[\"bat\"]");
+        let first = requests.recv().unwrap();
+        assert!(!first.contains("if-none-match"));
+
+        assert_eq!(transport.fetch("names.json").await.unwrap().unwrap(), b"[\"bat\"]");
+        let second = requests.recv().unwrap();
+        assert!(second.contains("if-none-match: \"v1\""));
+        assert!(second.contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt"));
+    }
+
+    #[tokio::test]
+    async fn fresh_responses_and_missing_files_are_served_from_the_cache() {
+        let (root, requests) = serve(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let (_dir, transport) = transport(&root, 60);
+
+        assert!(transport.fetch("packages/fd.json").await.unwrap().is_none());
+        requests.recv().unwrap();
+        assert!(transport.fetch("packages/fd.json").await.unwrap().is_none());
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn local_files_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("proto_version"), "4").unwrap();
+        let transport = Transport::new(dir.path().to_str().unwrap(), "0.0.0", None).unwrap();
+        assert_eq!(transport.fetch("proto_version").await.unwrap().unwrap(), b"4");
+        assert!(transport.fetch("names.json").await.unwrap().is_none());
+    }
+}